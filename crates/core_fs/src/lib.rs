@@ -1,3 +1,6 @@
+mod watcher;
+pub use watcher::FileWatcher;
+
 pub fn load(file: &'static str) -> Vec<u8> {
     /*
     // Debugging for file paths
@@ -9,6 +12,5 @@ pub fn load(file: &'static str) -> Vec<u8> {
 
     */
 
-    let data = std::fs::read(&file).unwrap();
-    data
+    std::fs::read(file).unwrap()
 }
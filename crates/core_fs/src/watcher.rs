@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// What a file looked like when it was last checked. `None` if it couldn't be read.
+type Stamp = Option<(SystemTime, u64)>;
+
+/// Watches files for changes by polling their modification time and length.
+pub struct FileWatcher {
+    interval: Duration,
+    last_poll: Option<Instant>,
+    files: Vec<(PathBuf, Stamp)>,
+}
+
+impl FileWatcher {
+    /// Creates a watcher that checks the files at most once every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_poll: None,
+            files: vec![],
+        }
+    }
+
+    /// Starts watching the file, if it isn't already. It doesn't have to exist yet.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref().to_path_buf();
+        if self.files.iter().any(|(watched, _)| *watched == path) {
+            return;
+        }
+
+        let stamp = stamp(&path);
+        self.files.push((path, stamp));
+    }
+
+    /// Returns the files that were changed, created or deleted since the last check.
+    /// Returns nothing if it's too soon to check again.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        if let Some(last_poll) = self.last_poll {
+            if now - last_poll < self.interval {
+                return vec![];
+            }
+        }
+        self.last_poll = Some(now);
+
+        let mut changed = vec![];
+        for (path, last_stamp) in self.files.iter_mut() {
+            let stamp = stamp(path);
+            if stamp != *last_stamp {
+                *last_stamp = stamp;
+                changed.push(path.clone());
+            }
+        }
+
+        changed
+    }
+}
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_changed_files() {
+        let dir = std::env::temp_dir().join(format!("core_fs_watcher_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("watched.cfg");
+        std::fs::write(&path, "a").unwrap();
+
+        let mut watcher = FileWatcher::new(Duration::from_secs(0));
+        watcher.watch(&path);
+        watcher.watch(&path);
+        assert!(watcher.poll().is_empty());
+
+        // The length changes, so this is seen even if the modification time doesn't
+        std::fs::write(&path, "ab").unwrap();
+        assert_eq!(watcher.poll(), vec![path.clone()]);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(watcher.poll(), vec![path.clone()]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -17,15 +17,23 @@ impl Rgba8Image {
     }
 
     pub fn load<P>(path: P) -> Self
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Self::open(path).unwrap()
+    }
+
+    /// Loads the image, returning an error if it can't be read or decoded.
+    pub fn open<P>(path: P) -> Result<Self, String>
     where
         P: AsRef<std::path::Path>,
     {
         use image::io::Reader as ImageReader;
 
         let img = ImageReader::open(path)
-            .unwrap()
+            .map_err(|e| format!("{:?}", e))?
             .decode()
-            .unwrap()
+            .map_err(|e| format!("{:?}", e))?
             .to_rgba8();
 
         let width = img.width();
@@ -33,7 +41,7 @@ impl Rgba8Image {
 
         let img = DynamicImage::ImageRgba8(img);
 
-        Self { img, width, height }
+        Ok(Self { img, width, height })
     }
 
     /// Puts a pixel at the given coordinates.
@@ -0,0 +1,353 @@
+use crate::{KeyboardMsg, Modifiers, WindowMsg};
+use std::fmt;
+
+/// A key, along with the modifiers that must be held for it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct KeyBinding {
+    pub key: KeyboardMsg,
+    pub modifiers: Modifiers,
+}
+
+impl KeyBinding {
+    pub fn new(key: KeyboardMsg) -> Self {
+        Self {
+            key,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    /// Parses a key with optional modifiers, such as `W` or `Ctrl+Shift+S`. Case insensitive.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = Modifiers::default();
+        let mut parts: Vec<&str> = text.split('+').map(|part| part.trim()).collect();
+        let key = KeyboardMsg::from_name(parts.pop()?)?;
+
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "shift" => modifiers.shift = true,
+                "ctrl" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "logo" => modifiers.logo = true,
+                _ => return None,
+            }
+        }
+
+        Some(Self { key, modifiers })
+    }
+}
+
+/// Errors from parsing a bindings config. Lines start at 1.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BindingError {
+    /// The line isn't of the form `action = key`
+    Syntax {
+        line: usize,
+    },
+    UnknownAction {
+        line: usize,
+        action: String,
+    },
+    UnknownKey {
+        line: usize,
+        key: String,
+    },
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingError::Syntax { line } => {
+                write!(f, "line {}: expected `action = key`", line)
+            }
+            BindingError::UnknownAction { line, action } => {
+                write!(f, "line {}: unknown action `{}`", line, action)
+            }
+            BindingError::UnknownKey { line, key } => {
+                write!(f, "line {}: unknown key `{}`", line, key)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BindingError {}
+
+/// An action starting or stopping.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ActionMsg<Action> {
+    /// Sent once per press. Key repeats are ignored.
+    Pressed(Action),
+    Released(Action),
+}
+
+/// Maps keys to actions, so simulations can react to what the user wants rather than raw keys.
+pub struct Bindings<Action> {
+    bindings: Vec<(KeyBinding, Action)>,
+    modifiers: Modifiers,
+    /// Actions currently held down, along with the key that started them
+    held: Vec<(KeyboardMsg, Action)>,
+}
+
+impl<Action> Bindings<Action>
+where
+    Action: Copy + PartialEq,
+{
+    pub fn new() -> Self {
+        Self {
+            bindings: vec![],
+            modifiers: Modifiers::default(),
+            held: vec![],
+        }
+    }
+
+    /// Parses a config of `action = key` lines. Several keys may be given, separated by commas.
+    /// Blank lines and anything after a `#` are ignored.
+    /// `action_from_name` maps the action names onto actions.
+    ///
+    /// ```text
+    /// # Movement
+    /// move_forward = W, Up
+    /// save = Ctrl+S
+    /// ```
+    pub fn parse(
+        config: &str,
+        action_from_name: impl Fn(&str) -> Option<Action>,
+    ) -> Result<Self, BindingError> {
+        let mut bindings = Self::new();
+
+        for (i, line) in config.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut sides = line.splitn(2, '=');
+            let (name, keys) = match (sides.next(), sides.next()) {
+                (Some(name), Some(keys)) => (name.trim(), keys),
+                _ => return Err(BindingError::Syntax { line: line_number }),
+            };
+
+            let action = action_from_name(name).ok_or_else(|| BindingError::UnknownAction {
+                line: line_number,
+                action: name.to_string(),
+            })?;
+
+            for key in keys.split(',').map(|key| key.trim()) {
+                let binding = KeyBinding::parse(key).ok_or_else(|| BindingError::UnknownKey {
+                    line: line_number,
+                    key: key.to_string(),
+                })?;
+
+                bindings.bind(binding, action);
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn bind(&mut self, binding: KeyBinding, action: Action) {
+        self.bindings.push((binding, action));
+    }
+
+    /// Turns a window message into an action message, if it triggers one.
+    /// Should be given every window message, so modifiers are tracked.
+    pub fn handle(&mut self, msg: &WindowMsg) -> Option<ActionMsg<Action>> {
+        match msg {
+            WindowMsg::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+                None
+            }
+            WindowMsg::KeyPress(key) => {
+                // Key repeat. The action started on the first press and lasts until the release
+                if self.held.iter().any(|(held_key, _)| held_key == key) {
+                    return None;
+                }
+
+                let action = self.action_for(*key)?;
+                self.held.push((*key, action));
+
+                Some(ActionMsg::Pressed(action))
+            }
+            WindowMsg::KeyRelease(key) => {
+                // Release whatever the key started, even if the modifiers changed since
+                let i = self.held.iter().position(|(held_key, _)| held_key == key)?;
+                let (_, action) = self.held.remove(i);
+
+                Some(ActionMsg::Released(action))
+            }
+            _ => None,
+        }
+    }
+
+    /// The action bound to the key, preferring the binding that uses the most of the held modifiers.
+    fn action_for(&self, key: KeyboardMsg) -> Option<Action> {
+        self.bindings
+            .iter()
+            .filter(|(binding, _)| binding.key == key && self.modifiers.contains(binding.modifiers))
+            .max_by_key(|(binding, _)| binding.modifiers.count())
+            .map(|(_, action)| *action)
+    }
+}
+
+impl<Action> Default for Bindings<Action>
+where
+    Action: Copy + PartialEq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum TestAction {
+        Forward,
+        Save,
+        SaveAs,
+    }
+
+    fn action_from_name(name: &str) -> Option<TestAction> {
+        match name {
+            "forward" => Some(TestAction::Forward),
+            "save" => Some(TestAction::Save),
+            "save_as" => Some(TestAction::SaveAs),
+            _ => None,
+        }
+    }
+
+    fn ctrl(shift: bool) -> WindowMsg {
+        WindowMsg::ModifiersChanged(Modifiers {
+            ctrl: true,
+            shift,
+            ..Modifiers::default()
+        })
+    }
+
+    #[test]
+    fn key_binding_parse() {
+        assert_eq!(
+            Some(KeyBinding::new(KeyboardMsg::W)),
+            KeyBinding::parse("w")
+        );
+
+        let binding = KeyBinding::parse("Ctrl + Shift+S").unwrap();
+        assert_eq!(KeyboardMsg::S, binding.key);
+        assert!(binding.modifiers.ctrl);
+        assert!(binding.modifiers.shift);
+        assert!(!binding.modifiers.alt);
+
+        assert_eq!(None, KeyBinding::parse("Hyper+S"));
+        assert_eq!(None, KeyBinding::parse("Ctrl+"));
+    }
+
+    #[test]
+    fn bindings_parse_errors() {
+        let config = "forward = W\n\n# comment\njump = Space";
+        let err = Bindings::parse(config, action_from_name).err();
+        assert_eq!(
+            Some(BindingError::UnknownAction {
+                line: 4,
+                action: "jump".to_string()
+            }),
+            err
+        );
+
+        let err = Bindings::parse("forward = W, Nope", action_from_name).err();
+        assert_eq!(
+            Some(BindingError::UnknownKey {
+                line: 1,
+                key: "Nope".to_string()
+            }),
+            err
+        );
+
+        let err = Bindings::parse("forward W", action_from_name).err();
+        assert_eq!(Some(BindingError::Syntax { line: 1 }), err);
+    }
+
+    #[test]
+    fn bindings_handle_press_and_release() {
+        let mut bindings = Bindings::parse("forward = W, Up # move", action_from_name).unwrap();
+
+        assert_eq!(
+            Some(ActionMsg::Pressed(TestAction::Forward)),
+            bindings.handle(&WindowMsg::KeyPress(KeyboardMsg::Up))
+        );
+        assert_eq!(
+            Some(ActionMsg::Released(TestAction::Forward)),
+            bindings.handle(&WindowMsg::KeyRelease(KeyboardMsg::Up))
+        );
+        assert_eq!(None, bindings.handle(&WindowMsg::KeyPress(KeyboardMsg::S)));
+        assert_eq!(
+            None,
+            bindings.handle(&WindowMsg::KeyRelease(KeyboardMsg::S))
+        );
+    }
+
+    #[test]
+    fn bindings_prefer_most_modifiers() {
+        let config = "forward = S\nsave = Ctrl+S\nsave_as = Ctrl+Shift+S";
+        let mut bindings = Bindings::parse(config, action_from_name).unwrap();
+        let press = WindowMsg::KeyPress(KeyboardMsg::S);
+        let release = WindowMsg::KeyRelease(KeyboardMsg::S);
+
+        assert_eq!(
+            Some(ActionMsg::Pressed(TestAction::Forward)),
+            bindings.handle(&press)
+        );
+        bindings.handle(&release);
+
+        bindings.handle(&ctrl(false));
+        assert_eq!(
+            Some(ActionMsg::Pressed(TestAction::Save)),
+            bindings.handle(&press)
+        );
+        bindings.handle(&release);
+
+        bindings.handle(&ctrl(true));
+        assert_eq!(
+            Some(ActionMsg::Pressed(TestAction::SaveAs)),
+            bindings.handle(&press)
+        );
+    }
+
+    #[test]
+    fn bindings_ignore_key_repeat() {
+        let mut bindings = Bindings::parse(
+            "forward = S
+save = Ctrl+S",
+            action_from_name,
+        )
+        .unwrap();
+        let press = WindowMsg::KeyPress(KeyboardMsg::S);
+
+        assert_eq!(
+            Some(ActionMsg::Pressed(TestAction::Forward)),
+            bindings.handle(&press)
+        );
+        bindings.handle(&ctrl(false));
+
+        assert_eq!(None, bindings.handle(&press));
+        assert_eq!(
+            Some(ActionMsg::Released(TestAction::Forward)),
+            bindings.handle(&WindowMsg::KeyRelease(KeyboardMsg::S))
+        );
+    }
+
+    #[test]
+    fn bindings_release_after_modifiers_change() {
+        let mut bindings = Bindings::parse("save = Ctrl+S", action_from_name).unwrap();
+
+        bindings.handle(&ctrl(false));
+        bindings.handle(&WindowMsg::KeyPress(KeyboardMsg::S));
+        bindings.handle(&WindowMsg::ModifiersChanged(Modifiers::default()));
+
+        assert_eq!(
+            Some(ActionMsg::Released(TestAction::Save)),
+            bindings.handle(&WindowMsg::KeyRelease(KeyboardMsg::S))
+        );
+    }
+}
@@ -0,0 +1,115 @@
+/// Defines `KeyboardMsg`, along with the names used for each key in config files.
+macro_rules! keys {
+    ($($key:ident),* $(,)?) => {
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum KeyboardMsg {
+            $($key),*
+        }
+
+        impl KeyboardMsg {
+            /// Every key, in declaration order.
+            pub const ALL: &'static [KeyboardMsg] = &[$(KeyboardMsg::$key),*];
+
+            /// The name of the key, as used in config files.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(KeyboardMsg::$key => stringify!($key)),*
+                }
+            }
+        }
+    };
+}
+
+keys! {
+    // Letters
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    // Number row
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    // Function keys
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    // Arrows and navigation
+    Up, Down, Left, Right, Home, End, PageUp, PageDown, Insert, Delete,
+    // Editing and control
+    Escape, Space, Enter, Tab, Backspace, CapsLock, PrintScreen, ScrollLock, Pause,
+    // Punctuation
+    Minus, Equals, LBracket, RBracket, Backslash, Semicolon, Apostrophe, Comma, Period, Slash, Grave,
+    // Modifiers
+    LShift, RShift, LControl, RControl, LAlt, RAlt, LWin, RWin,
+    // Numpad
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadDecimal, NumpadEnter,
+}
+
+impl KeyboardMsg {
+    /// Looks up a key by its name. Case insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|key| key.name().eq_ignore_ascii_case(name))
+            .copied()
+    }
+}
+
+/// The modifier keys held down. Either side counts.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The Windows or Command key
+    pub logo: bool,
+}
+
+impl Modifiers {
+    /// Whether every modifier set in `other` is also set here.
+    pub fn contains(&self, other: Modifiers) -> bool {
+        (self.shift || !other.shift)
+            && (self.ctrl || !other.ctrl)
+            && (self.alt || !other.alt)
+            && (self.logo || !other.logo)
+    }
+
+    /// The number of modifiers set.
+    pub fn count(&self) -> u32 {
+        self.shift as u32 + self.ctrl as u32 + self.alt as u32 + self.logo as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyboard_msg_from_name() {
+        assert_eq!(Some(KeyboardMsg::W), KeyboardMsg::from_name("W"));
+        assert_eq!(Some(KeyboardMsg::W), KeyboardMsg::from_name("w"));
+        assert_eq!(Some(KeyboardMsg::Key8), KeyboardMsg::from_name("key8"));
+        assert_eq!(Some(KeyboardMsg::PageUp), KeyboardMsg::from_name("PageUp"));
+        assert_eq!(None, KeyboardMsg::from_name("NotAKey"));
+    }
+
+    #[test]
+    fn keyboard_msg_names_round_trip() {
+        for key in KeyboardMsg::ALL {
+            assert_eq!(Some(*key), KeyboardMsg::from_name(key.name()));
+        }
+    }
+
+    #[test]
+    fn modifiers_contains() {
+        let ctrl_shift = Modifiers {
+            ctrl: true,
+            shift: true,
+            ..Modifiers::default()
+        };
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::default()
+        };
+
+        assert!(ctrl_shift.contains(ctrl));
+        assert!(ctrl_shift.contains(Modifiers::default()));
+        assert!(!ctrl.contains(ctrl_shift));
+        assert_eq!(2, ctrl_shift.count());
+    }
+}
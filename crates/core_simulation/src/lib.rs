@@ -1,5 +1,10 @@
 use std::marker::PhantomData;
 
+mod bindings;
+mod keyboard;
+pub use bindings::*;
+pub use keyboard::*;
+
 use core_data_structures::queue::Queue;
 pub use core_renderer::{RenderBuilder, RenderCommand};
 pub use core_timing::Duration;
@@ -25,17 +30,31 @@ pub enum Input<UserMsg> {
 pub enum WindowMsg {
     Shutdown,
     RedrawRequested,
-    Resize { w: u32, h: u32 },
+    Resize {
+        w: u32,
+        h: u32,
+    },
     KeyPress(KeyboardMsg),
     KeyRelease(KeyboardMsg),
+    ModifiersChanged(Modifiers),
+    /// Cursor position in pixels, relative to the top left of the window
+    CursorMove {
+        x: f32,
+        y: f32,
+    },
+    MousePress(MouseButton),
+    MouseRelease(MouseButton),
+    /// Number of lines scrolled. Positive is scrolling away from the user.
+    MouseWheel {
+        lines: f32,
+    },
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
-pub enum KeyboardMsg {
-    W,
-    A,
-    S,
-    D,
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
 }
 
 /// Common functionality a simulation must implement.
@@ -140,7 +159,7 @@ where
                 control_msg = self.sim.tick(
                     self.frame,
                     self.time_keeper.tick_duration,
-                    &self.engine_queue.items(),
+                    self.engine_queue.items(),
                 );
                 self.engine_queue.clear();
 
@@ -158,7 +177,7 @@ where
             let delta_t = self.time_keeper.simulation_stopwatch.elapsed();
             control_msg = self
                 .sim
-                .tick(self.frame, delta_t, &self.engine_queue.items());
+                .tick(self.frame, delta_t, self.engine_queue.items());
             self.engine_queue.clear();
         }
 
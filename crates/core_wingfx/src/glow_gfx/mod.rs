@@ -10,6 +10,9 @@ use glow_render::make;
 
 use crate::wingfx::WinGfx;
 
+/// Touchpads scroll in pixels; this converts them to roughly the lines a mouse wheel reports.
+const PIXELS_PER_LINE: f32 = 20.;
+
 pub struct OpenGlWindow {
     title: &'static str,
     w: u32,
@@ -40,33 +43,51 @@ impl OpenGlWindow {
                         glutin::event::ElementState::Released => false,
                     };
 
-                    let mut key_msg = core_simulation::KeyboardMsg::W;
-
-                    if let Some(keycode) = input.virtual_keycode {
-                        match keycode {
-                            glutin::event::VirtualKeyCode::W => {
-                                key_msg = core_simulation::KeyboardMsg::W;
-                            }
-                            glutin::event::VirtualKeyCode::A => {
-                                key_msg = core_simulation::KeyboardMsg::A;
-                            }
-                            glutin::event::VirtualKeyCode::S => {
-                                key_msg = core_simulation::KeyboardMsg::S;
-                            }
-                            glutin::event::VirtualKeyCode::D => {
-                                key_msg = core_simulation::KeyboardMsg::D;
-                            }
-                            _ => {}
-                        }
+                    // Keys the simulation doesn't know about are dropped
+                    let key_msg = input.virtual_keycode.and_then(keyboard_msg)?;
+
+                    if pressed {
+                        Some(WindowMsg::KeyPress(key_msg))
+                    } else {
+                        Some(WindowMsg::KeyRelease(key_msg))
+                    }
+                }
+                WindowEvent::ModifiersChanged(state) => {
+                    Some(WindowMsg::ModifiersChanged(core_simulation::Modifiers {
+                        shift: state.shift(),
+                        ctrl: state.ctrl(),
+                        alt: state.alt(),
+                        logo: state.logo(),
+                    }))
+                }
+                WindowEvent::CursorMoved { position, .. } => Some(WindowMsg::CursorMove {
+                    x: position.x as f32,
+                    y: position.y as f32,
+                }),
+                WindowEvent::MouseInput { state, button, .. } => {
+                    let button = match button {
+                        glutin::event::MouseButton::Left => core_simulation::MouseButton::Left,
+                        glutin::event::MouseButton::Right => core_simulation::MouseButton::Right,
+                        glutin::event::MouseButton::Middle => core_simulation::MouseButton::Middle,
+                        glutin::event::MouseButton::Other(_) => return None,
+                    };
 
-                        if pressed {
-                            return Some(WindowMsg::KeyPress(key_msg));
-                        } else {
-                            return Some(WindowMsg::KeyRelease(key_msg));
+                    match state {
+                        glutin::event::ElementState::Pressed => Some(WindowMsg::MousePress(button)),
+                        glutin::event::ElementState::Released => {
+                            Some(WindowMsg::MouseRelease(button))
                         }
                     }
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let lines = match delta {
+                        glutin::event::MouseScrollDelta::LineDelta(_, y) => y,
+                        glutin::event::MouseScrollDelta::PixelDelta(position) => {
+                            position.y as f32 / PIXELS_PER_LINE
+                        }
+                    };
 
-                    None
+                    Some(WindowMsg::MouseWheel { lines })
                 }
                 _ => None,
             },
@@ -125,6 +146,11 @@ where
                         }
                         WindowMsg::KeyPress(_) => {}
                         WindowMsg::KeyRelease(_) => {}
+                        WindowMsg::ModifiersChanged(_) => {}
+                        WindowMsg::CursorMove { .. } => {}
+                        WindowMsg::MousePress(_) => {}
+                        WindowMsg::MouseRelease(_) => {}
+                        WindowMsg::MouseWheel { .. } => {}
                     }
 
                     Some(Input::WindowMsg(*ev))
@@ -147,3 +173,118 @@ where
         });
     }
 }
+
+/// Maps glutin's keys onto the simulation's. Returns `None` for keys the simulation doesn't have.
+fn keyboard_msg(keycode: glutin::event::VirtualKeyCode) -> Option<core_simulation::KeyboardMsg> {
+    use core_simulation::KeyboardMsg;
+    use glutin::event::VirtualKeyCode;
+
+    let key = match keycode {
+        VirtualKeyCode::A => KeyboardMsg::A,
+        VirtualKeyCode::B => KeyboardMsg::B,
+        VirtualKeyCode::C => KeyboardMsg::C,
+        VirtualKeyCode::D => KeyboardMsg::D,
+        VirtualKeyCode::E => KeyboardMsg::E,
+        VirtualKeyCode::F => KeyboardMsg::F,
+        VirtualKeyCode::G => KeyboardMsg::G,
+        VirtualKeyCode::H => KeyboardMsg::H,
+        VirtualKeyCode::I => KeyboardMsg::I,
+        VirtualKeyCode::J => KeyboardMsg::J,
+        VirtualKeyCode::K => KeyboardMsg::K,
+        VirtualKeyCode::L => KeyboardMsg::L,
+        VirtualKeyCode::M => KeyboardMsg::M,
+        VirtualKeyCode::N => KeyboardMsg::N,
+        VirtualKeyCode::O => KeyboardMsg::O,
+        VirtualKeyCode::P => KeyboardMsg::P,
+        VirtualKeyCode::Q => KeyboardMsg::Q,
+        VirtualKeyCode::R => KeyboardMsg::R,
+        VirtualKeyCode::S => KeyboardMsg::S,
+        VirtualKeyCode::T => KeyboardMsg::T,
+        VirtualKeyCode::U => KeyboardMsg::U,
+        VirtualKeyCode::V => KeyboardMsg::V,
+        VirtualKeyCode::W => KeyboardMsg::W,
+        VirtualKeyCode::X => KeyboardMsg::X,
+        VirtualKeyCode::Y => KeyboardMsg::Y,
+        VirtualKeyCode::Z => KeyboardMsg::Z,
+        VirtualKeyCode::Key0 => KeyboardMsg::Key0,
+        VirtualKeyCode::Key1 => KeyboardMsg::Key1,
+        VirtualKeyCode::Key2 => KeyboardMsg::Key2,
+        VirtualKeyCode::Key3 => KeyboardMsg::Key3,
+        VirtualKeyCode::Key4 => KeyboardMsg::Key4,
+        VirtualKeyCode::Key5 => KeyboardMsg::Key5,
+        VirtualKeyCode::Key6 => KeyboardMsg::Key6,
+        VirtualKeyCode::Key7 => KeyboardMsg::Key7,
+        VirtualKeyCode::Key8 => KeyboardMsg::Key8,
+        VirtualKeyCode::Key9 => KeyboardMsg::Key9,
+        VirtualKeyCode::F1 => KeyboardMsg::F1,
+        VirtualKeyCode::F2 => KeyboardMsg::F2,
+        VirtualKeyCode::F3 => KeyboardMsg::F3,
+        VirtualKeyCode::F4 => KeyboardMsg::F4,
+        VirtualKeyCode::F5 => KeyboardMsg::F5,
+        VirtualKeyCode::F6 => KeyboardMsg::F6,
+        VirtualKeyCode::F7 => KeyboardMsg::F7,
+        VirtualKeyCode::F8 => KeyboardMsg::F8,
+        VirtualKeyCode::F9 => KeyboardMsg::F9,
+        VirtualKeyCode::F10 => KeyboardMsg::F10,
+        VirtualKeyCode::F11 => KeyboardMsg::F11,
+        VirtualKeyCode::F12 => KeyboardMsg::F12,
+        VirtualKeyCode::Up => KeyboardMsg::Up,
+        VirtualKeyCode::Down => KeyboardMsg::Down,
+        VirtualKeyCode::Left => KeyboardMsg::Left,
+        VirtualKeyCode::Right => KeyboardMsg::Right,
+        VirtualKeyCode::Home => KeyboardMsg::Home,
+        VirtualKeyCode::End => KeyboardMsg::End,
+        VirtualKeyCode::PageUp => KeyboardMsg::PageUp,
+        VirtualKeyCode::PageDown => KeyboardMsg::PageDown,
+        VirtualKeyCode::Insert => KeyboardMsg::Insert,
+        VirtualKeyCode::Delete => KeyboardMsg::Delete,
+        VirtualKeyCode::Escape => KeyboardMsg::Escape,
+        VirtualKeyCode::Space => KeyboardMsg::Space,
+        VirtualKeyCode::Tab => KeyboardMsg::Tab,
+        VirtualKeyCode::Pause => KeyboardMsg::Pause,
+        VirtualKeyCode::Minus => KeyboardMsg::Minus,
+        VirtualKeyCode::Equals => KeyboardMsg::Equals,
+        VirtualKeyCode::LBracket => KeyboardMsg::LBracket,
+        VirtualKeyCode::RBracket => KeyboardMsg::RBracket,
+        VirtualKeyCode::Backslash => KeyboardMsg::Backslash,
+        VirtualKeyCode::Semicolon => KeyboardMsg::Semicolon,
+        VirtualKeyCode::Apostrophe => KeyboardMsg::Apostrophe,
+        VirtualKeyCode::Comma => KeyboardMsg::Comma,
+        VirtualKeyCode::Period => KeyboardMsg::Period,
+        VirtualKeyCode::Slash => KeyboardMsg::Slash,
+        VirtualKeyCode::Grave => KeyboardMsg::Grave,
+        VirtualKeyCode::LShift => KeyboardMsg::LShift,
+        VirtualKeyCode::RShift => KeyboardMsg::RShift,
+        VirtualKeyCode::LControl => KeyboardMsg::LControl,
+        VirtualKeyCode::RControl => KeyboardMsg::RControl,
+        VirtualKeyCode::LAlt => KeyboardMsg::LAlt,
+        VirtualKeyCode::RAlt => KeyboardMsg::RAlt,
+        VirtualKeyCode::LWin => KeyboardMsg::LWin,
+        VirtualKeyCode::RWin => KeyboardMsg::RWin,
+        VirtualKeyCode::Numpad0 => KeyboardMsg::Numpad0,
+        VirtualKeyCode::Numpad1 => KeyboardMsg::Numpad1,
+        VirtualKeyCode::Numpad2 => KeyboardMsg::Numpad2,
+        VirtualKeyCode::Numpad3 => KeyboardMsg::Numpad3,
+        VirtualKeyCode::Numpad4 => KeyboardMsg::Numpad4,
+        VirtualKeyCode::Numpad5 => KeyboardMsg::Numpad5,
+        VirtualKeyCode::Numpad6 => KeyboardMsg::Numpad6,
+        VirtualKeyCode::Numpad7 => KeyboardMsg::Numpad7,
+        VirtualKeyCode::Numpad8 => KeyboardMsg::Numpad8,
+        VirtualKeyCode::Numpad9 => KeyboardMsg::Numpad9,
+        VirtualKeyCode::NumpadAdd => KeyboardMsg::NumpadAdd,
+        VirtualKeyCode::NumpadSubtract => KeyboardMsg::NumpadSubtract,
+        VirtualKeyCode::NumpadMultiply => KeyboardMsg::NumpadMultiply,
+        VirtualKeyCode::NumpadDivide => KeyboardMsg::NumpadDivide,
+        VirtualKeyCode::NumpadDecimal => KeyboardMsg::NumpadDecimal,
+        VirtualKeyCode::NumpadEnter => KeyboardMsg::NumpadEnter,
+        // Named differently by glutin
+        VirtualKeyCode::Return => KeyboardMsg::Enter,
+        VirtualKeyCode::Back => KeyboardMsg::Backspace,
+        VirtualKeyCode::Capital => KeyboardMsg::CapsLock,
+        VirtualKeyCode::Snapshot => KeyboardMsg::PrintScreen,
+        VirtualKeyCode::Scroll => KeyboardMsg::ScrollLock,
+        _ => return None,
+    };
+
+    Some(key)
+}
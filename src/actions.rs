@@ -0,0 +1,70 @@
+// The actions the user can trigger, and the key bindings for them.
+// Bindings are loaded from `res/bindings.cfg`, falling back to the defaults built into the executable.
+
+use crate::camera_controllers::FlyControl;
+use crate::core_raytracer::DebugMode;
+use core_simulation::Bindings;
+
+const BINDINGS_PATH: &str = "res/bindings.cfg";
+const DEFAULT_BINDINGS: &str = include_str!("../res/bindings.cfg");
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    Fly(FlyControl),
+    DebugView(DebugMode),
+    /// Switches between the RGB and spectral integrators
+    ToggleSpectral,
+    CycleInterlace,
+    CycleProjection,
+    /// Removes the last object picked with the mouse
+    RemoveSelected,
+}
+
+impl Action {
+    /// Looks up an action by the name used in the bindings config.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let action = match name {
+            "move_forward" => Action::Fly(FlyControl::Forward),
+            "move_back" => Action::Fly(FlyControl::Back),
+            "move_left" => Action::Fly(FlyControl::Left),
+            "move_right" => Action::Fly(FlyControl::Right),
+            "look_up" => Action::Fly(FlyControl::LookUp),
+            "look_down" => Action::Fly(FlyControl::LookDown),
+            "look_left" => Action::Fly(FlyControl::LookLeft),
+            "look_right" => Action::Fly(FlyControl::LookRight),
+            "debug_off" => Action::DebugView(DebugMode::Off),
+            "debug_normals" => Action::DebugView(DebugMode::Normals),
+            "debug_depth" => Action::DebugView(DebugMode::Depth),
+            "debug_uvs" => Action::DebugView(DebugMode::Uvs),
+            "debug_traversal_cost" => Action::DebugView(DebugMode::TraversalCost),
+            "debug_bounces" => Action::DebugView(DebugMode::Bounces),
+            "debug_sample_count" => Action::DebugView(DebugMode::SampleCount),
+            "debug_material_id" => Action::DebugView(DebugMode::MaterialId),
+            "toggle_spectral" => Action::ToggleSpectral,
+            "cycle_interlace" => Action::CycleInterlace,
+            "cycle_projection" => Action::CycleProjection,
+            "remove_selected" => Action::RemoveSelected,
+            _ => return None,
+        };
+
+        Some(action)
+    }
+}
+
+/// Loads the user's bindings. If they're missing or invalid, the error is printed and the defaults are used.
+pub fn load_bindings() -> Bindings<Action> {
+    let loaded = match std::fs::read_to_string(BINDINGS_PATH) {
+        Ok(config) => Bindings::parse(&config, Action::from_name)
+            .map_err(|e| format!("{}: {}", BINDINGS_PATH, e)),
+        Err(e) => Err(format!("Unable to read {}: {}", BINDINGS_PATH, e)),
+    };
+
+    match loaded {
+        Ok(bindings) => bindings,
+        Err(e) => {
+            println!("{}. Using the default bindings.", e);
+            Bindings::parse(DEFAULT_BINDINGS, Action::from_name)
+                .expect("The default bindings should be valid")
+        }
+    }
+}
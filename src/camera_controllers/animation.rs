@@ -0,0 +1,208 @@
+// Keyframed camera animation. Every property of the camera is interpolated between the keyframes around the
+// sampled time, so the track can also zoom and pull focus.
+
+use super::{length, normalize};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interpolation {
+    /// Straight lines between keyframes
+    Linear,
+    /// A smooth curve through every keyframe, keeping the camera moving through them
+    CatmullRom,
+    /// Cubic Bézier segments with flat handles, so the camera eases in and out of every keyframe
+    Bezier,
+}
+
+/// Everything about the camera that can be animated.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraPose {
+    pub eye: (f32, f32, f32),
+    pub target: (f32, f32, f32),
+    pub up: (f32, f32, f32),
+    pub v_fov_deg: f32,
+    pub focus_distance: f32,
+    /// `0` keeps everything in focus
+    pub aperture: f32,
+}
+
+impl Default for CameraPose {
+    /// Looks down -Z from the origin with a 90 degree FOV, with everything in focus.
+    fn default() -> Self {
+        Self {
+            eye: (0., 0., 0.),
+            target: (0., 0., -1.),
+            up: (0., 1., 0.),
+            v_fov_deg: 90.,
+            focus_distance: 1.,
+            aperture: 0.,
+        }
+    }
+}
+
+/// The number of values in a pose, when flattened for interpolation.
+const POSE_LEN: usize = 12;
+
+impl CameraPose {
+    fn to_array(&self) -> [f32; POSE_LEN] {
+        [
+            self.eye.0,
+            self.eye.1,
+            self.eye.2,
+            self.target.0,
+            self.target.1,
+            self.target.2,
+            self.up.0,
+            self.up.1,
+            self.up.2,
+            self.v_fov_deg,
+            self.focus_distance,
+            self.aperture,
+        ]
+    }
+
+    /// Builds a pose from interpolated values, fixing up any that overshot into something invalid.
+    fn from_array(a: [f32; POSE_LEN]) -> Self {
+        let up = [a[6], a[7], a[8]];
+        let up = if length(up) > 0. {
+            normalize(up)
+        } else {
+            [0., 1., 0.]
+        };
+
+        Self {
+            eye: (a[0], a[1], a[2]),
+            target: (a[3], a[4], a[5]),
+            up: (up[0], up[1], up[2]),
+            v_fov_deg: a[9].max(MIN_FOV_DEG).min(MAX_FOV_DEG),
+            focus_distance: a[10].max(MIN_FOCUS_DISTANCE),
+            aperture: a[11].max(0.),
+        }
+    }
+}
+
+const MIN_FOV_DEG: f32 = 1.;
+const MAX_FOV_DEG: f32 = 179.;
+const MIN_FOCUS_DISTANCE: f32 = 1e-3;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraKeyframe {
+    /// Seconds from the start of the animation
+    pub time: f32,
+    pub pose: CameraPose,
+}
+
+pub struct CameraTrack {
+    interpolation: Interpolation,
+    /// Sorted by time
+    keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraTrack {
+    /// Creates a track from the keyframes, which are sorted by time. Returns `None` if there are none.
+    pub fn new(interpolation: Interpolation, mut keyframes: Vec<CameraKeyframe>) -> Option<Self> {
+        if keyframes.is_empty() {
+            return None;
+        }
+
+        keyframes.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Some(Self {
+            interpolation,
+            keyframes,
+        })
+    }
+
+    /// The time of the first keyframe.
+    pub fn start_time(&self) -> f32 {
+        self.keyframes[0].time
+    }
+
+    /// The time from the first keyframe to the last.
+    pub fn duration(&self) -> f32 {
+        self.keyframes[self.keyframes.len() - 1].time - self.start_time()
+    }
+
+    /// The pose at the given time. Times outside of the track hold the first or last keyframe.
+    pub fn sample(&self, time: f32) -> CameraPose {
+        let keys = &self.keyframes;
+        let last = keys.len() - 1;
+
+        if time <= keys[0].time {
+            return keys[0].pose;
+        }
+        if time >= keys[last].time {
+            return keys[last].pose;
+        }
+
+        // The segment runs from keyframe `i` to `i + 1`
+        let i = keys
+            .iter()
+            .rposition(|key| key.time <= time)
+            .unwrap_or(0)
+            .min(last - 1);
+        let (t0, t1) = (keys[i].time, keys[i + 1].time);
+        let dt = t1 - t0;
+        let u = if dt > 0. { (time - t0) / dt } else { 0. };
+
+        let p0 = keys[i].pose.to_array();
+        let p1 = keys[i + 1].pose.to_array();
+
+        let mut pose = [0.; POSE_LEN];
+        match self.interpolation {
+            Interpolation::Linear => {
+                for j in 0..POSE_LEN {
+                    pose[j] = p0[j] + (p1[j] - p0[j]) * u;
+                }
+            }
+            Interpolation::Bezier => {
+                // With both handles on their keyframes, the Bézier curve reduces to a smoothstep
+                let s = u * u * (3. - 2. * u);
+                for j in 0..POSE_LEN {
+                    pose[j] = p0[j] + (p1[j] - p0[j]) * s;
+                }
+            }
+            Interpolation::CatmullRom => {
+                // Cubic Hermite, with the tangents scaled from per second to per segment
+                let m0 = self.tangent(i);
+                let m1 = self.tangent(i + 1);
+
+                let u2 = u * u;
+                let u3 = u2 * u;
+                let h00 = 2. * u3 - 3. * u2 + 1.;
+                let h10 = u3 - 2. * u2 + u;
+                let h01 = -2. * u3 + 3. * u2;
+                let h11 = u3 - u2;
+
+                for j in 0..POSE_LEN {
+                    pose[j] = h00 * p0[j] + h10 * dt * m0[j] + h01 * p1[j] + h11 * dt * m1[j];
+                }
+            }
+        }
+
+        CameraPose::from_array(pose)
+    }
+
+    /// The Catmull-Rom tangent at the keyframe, per second. The ends use their only neighbor.
+    fn tangent(&self, i: usize) -> [f32; POSE_LEN] {
+        let keys = &self.keyframes;
+        let prev = i.saturating_sub(1);
+        let next = (i + 1).min(keys.len() - 1);
+
+        let p0 = keys[prev].pose.to_array();
+        let p1 = keys[next].pose.to_array();
+        let dt = keys[next].time - keys[prev].time;
+
+        let mut tangent = [0.; POSE_LEN];
+        if dt > 0. {
+            for j in 0..POSE_LEN {
+                tangent[j] = (p1[j] - p0[j]) / dt;
+            }
+        }
+
+        tangent
+    }
+}
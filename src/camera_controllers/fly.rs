@@ -0,0 +1,146 @@
+// First person camera, driven by held controls such as moving forward or looking up.
+// Held controls accelerate the camera towards its max speed, and it slows down again once they're released.
+
+use super::{direction, length, normalize, scale, sub, to_tuple, yaw_pitch, MAX_PITCH};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlyCameraSettings {
+    /// Units per second
+    pub max_speed: f32,
+    /// Units per second, per second. Also used to slow down.
+    pub acceleration: f32,
+    /// Radians per second
+    pub turn_speed: f32,
+}
+
+/// The ways the camera can be moved or turned.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FlyControl {
+    Forward,
+    Back,
+    Left,
+    Right,
+    LookUp,
+    LookDown,
+    LookLeft,
+    LookRight,
+}
+
+/// The controls currently held down.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct HeldControls {
+    forward: bool,
+    back: bool,
+    left: bool,
+    right: bool,
+    look_up: bool,
+    look_down: bool,
+    look_left: bool,
+    look_right: bool,
+}
+
+pub struct FlyCamera {
+    settings: FlyCameraSettings,
+    position: [f32; 3],
+    velocity: [f32; 3],
+    /// Radians around the Y axis. `0` looks down +X.
+    yaw: f32,
+    /// Radians above the horizon
+    pitch: f32,
+    held: HeldControls,
+}
+
+impl FlyCamera {
+    /// Creates a camera at `eye`, looking at `target`.
+    pub fn new(eye: (f32, f32, f32), target: (f32, f32, f32), settings: FlyCameraSettings) -> Self {
+        let (yaw, pitch) = yaw_pitch(eye, target);
+
+        Self {
+            settings,
+            position: [eye.0, eye.1, eye.2],
+            velocity: [0.; 3],
+            yaw,
+            pitch,
+            held: HeldControls::default(),
+        }
+    }
+
+    /// Moves the camera to `eye`, looking at `target`, and stops it. Held controls are kept.
+    pub fn set_pose(&mut self, eye: (f32, f32, f32), target: (f32, f32, f32)) {
+        let (yaw, pitch) = yaw_pitch(eye, target);
+
+        self.position = [eye.0, eye.1, eye.2];
+        self.velocity = [0.; 3];
+        self.yaw = yaw;
+        self.pitch = pitch;
+    }
+
+    /// Records a control being pressed or released.
+    pub fn set_control(&mut self, control: FlyControl, pressed: bool) {
+        let held = match control {
+            FlyControl::Forward => &mut self.held.forward,
+            FlyControl::Back => &mut self.held.back,
+            FlyControl::Left => &mut self.held.left,
+            FlyControl::Right => &mut self.held.right,
+            FlyControl::LookUp => &mut self.held.look_up,
+            FlyControl::LookDown => &mut self.held.look_down,
+            FlyControl::LookLeft => &mut self.held.look_left,
+            FlyControl::LookRight => &mut self.held.look_right,
+        };
+
+        *held = pressed;
+    }
+
+    /// Integrates the held controls over the timestep.
+    pub fn update(&mut self, delta_t: f32) {
+        let held = self.held;
+
+        self.yaw += axis(held.look_right, held.look_left) * self.settings.turn_speed * delta_t;
+        self.pitch += axis(held.look_up, held.look_down) * self.settings.turn_speed * delta_t;
+        self.pitch = self.pitch.max(-MAX_PITCH).min(MAX_PITCH);
+
+        // Accelerate towards the desired velocity, which is zero if nothing is held
+        let forward = direction(self.yaw, self.pitch);
+        let right = normalize([-forward[2], 0., forward[0]]);
+        let forward_amount = axis(held.forward, held.back);
+        let right_amount = axis(held.right, held.left);
+
+        let wish = normalize([
+            forward[0] * forward_amount + right[0] * right_amount,
+            forward[1] * forward_amount + right[1] * right_amount,
+            forward[2] * forward_amount + right[2] * right_amount,
+        ]);
+
+        let change = sub(scale(wish, self.settings.max_speed), self.velocity);
+        let max_change = self.settings.acceleration * delta_t;
+        let change = if length(change) > max_change {
+            scale(normalize(change), max_change)
+        } else {
+            change
+        };
+
+        for i in 0..3 {
+            self.velocity[i] += change[i];
+            self.position[i] += self.velocity[i] * delta_t;
+        }
+    }
+
+    pub fn eye(&self) -> (f32, f32, f32) {
+        to_tuple(self.position)
+    }
+
+    /// A point one unit in front of the camera.
+    pub fn target(&self) -> (f32, f32, f32) {
+        let forward = direction(self.yaw, self.pitch);
+        to_tuple([
+            self.position[0] + forward[0],
+            self.position[1] + forward[1],
+            self.position[2] + forward[2],
+        ])
+    }
+}
+
+/// `1` if only the positive control is held, `-1` if only the negative one is, otherwise `0`.
+fn axis(positive: bool, negative: bool) -> f32 {
+    positive as i32 as f32 - negative as i32 as f32
+}
@@ -0,0 +1,59 @@
+// Controllers that turn user input or animations into `Raytracer::look_at` calls.
+// The fly and orbit cameras share the same yaw/pitch convention: a yaw of `0` looks down +X and the pitch is
+// above the horizon.
+
+mod animation;
+mod fly;
+mod orbit;
+
+pub use animation::{CameraKeyframe, CameraPose, CameraTrack, Interpolation};
+pub use fly::{FlyCamera, FlyCameraSettings, FlyControl};
+pub use orbit::{OrbitCamera, OrbitCameraSettings};
+
+/// Keeps the camera from flipping over when looking straight up or down.
+const MAX_PITCH: f32 = 89. * std::f32::consts::PI / 180.;
+
+/// The unit direction for the yaw and pitch.
+fn direction(yaw: f32, pitch: f32) -> [f32; 3] {
+    [
+        pitch.cos() * yaw.cos(),
+        pitch.sin(),
+        pitch.cos() * yaw.sin(),
+    ]
+}
+
+/// The yaw and pitch looking from `eye` to `target`. The pitch is clamped.
+fn yaw_pitch(eye: (f32, f32, f32), target: (f32, f32, f32)) -> (f32, f32) {
+    let dir = normalize([target.0 - eye.0, target.1 - eye.1, target.2 - eye.2]);
+
+    (
+        dir[2].atan2(dir[0]),
+        dir[1].asin().max(-MAX_PITCH).min(MAX_PITCH),
+    )
+}
+
+fn to_tuple(a: [f32; 3]) -> (f32, f32, f32) {
+    (a[0], a[1], a[2])
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn length(a: [f32; 3]) -> f32 {
+    (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt()
+}
+
+/// Returns the zero vector unchanged.
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = length(a);
+    if len > 0. {
+        scale(a, 1. / len)
+    } else {
+        a
+    }
+}
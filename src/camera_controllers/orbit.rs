@@ -0,0 +1,124 @@
+// Orbit camera. Dragging with the left button rotates around the target, dragging with the right or
+// middle button pans the target, and the wheel zooms in and out.
+
+use super::{direction, length, scale, sub, to_tuple, yaw_pitch, MAX_PITCH};
+use core_simulation::MouseButton;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrbitCameraSettings {
+    /// Radians per pixel dragged
+    pub rotate_speed: f32,
+    /// Fraction of the distance to the target moved per pixel dragged
+    pub pan_speed: f32,
+    /// Fraction of the distance to the target zoomed per line scrolled
+    pub zoom_speed: f32,
+    /// The closest the camera may get to the target
+    pub min_distance: f32,
+}
+
+pub struct OrbitCamera {
+    settings: OrbitCameraSettings,
+    target: [f32; 3],
+    distance: f32,
+    /// Direction from the camera to the target. See `direction`.
+    yaw: f32,
+    pitch: f32,
+
+    /// Last cursor position, in window pixels
+    cursor: Option<(f32, f32)>,
+    rotating: bool,
+    panning: bool,
+}
+
+impl OrbitCamera {
+    /// Creates a camera at `eye`, orbiting `target`.
+    pub fn new(
+        eye: (f32, f32, f32),
+        target: (f32, f32, f32),
+        settings: OrbitCameraSettings,
+    ) -> Self {
+        let mut camera = Self {
+            settings,
+            target: [target.0, target.1, target.2],
+            distance: 0.,
+            yaw: 0.,
+            pitch: 0.,
+            cursor: None,
+            rotating: false,
+            panning: false,
+        };
+
+        camera.set_eye(eye);
+
+        camera
+    }
+
+    /// Moves the camera to `eye`, keeping the current target.
+    pub fn set_eye(&mut self, eye: (f32, f32, f32)) {
+        let target = to_tuple(self.target);
+        let (yaw, pitch) = yaw_pitch(eye, target);
+
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.distance =
+            length(sub(self.target, [eye.0, eye.1, eye.2])).max(self.settings.min_distance);
+    }
+
+    pub fn button(&mut self, button: MouseButton, pressed: bool) {
+        match button {
+            MouseButton::Left => self.rotating = pressed,
+            MouseButton::Right | MouseButton::Middle => self.panning = pressed,
+        }
+    }
+
+    /// Whether a button that rotates or pans is held.
+    pub fn is_dragging(&self) -> bool {
+        self.rotating || self.panning
+    }
+
+    /// Rotates or pans by how far the cursor moved since the last call, if a button is held.
+    pub fn cursor_moved(&mut self, x: f32, y: f32) {
+        if let Some((last_x, last_y)) = self.cursor {
+            let dx = x - last_x;
+            let dy = y - last_y;
+
+            if self.rotating {
+                // The camera moves opposite the drag, so the scene follows the cursor
+                self.yaw += dx * self.settings.rotate_speed;
+                self.pitch -= dy * self.settings.rotate_speed;
+                self.pitch = self.pitch.max(-MAX_PITCH).min(MAX_PITCH);
+            } else if self.panning {
+                let forward = direction(self.yaw, self.pitch);
+                let right = [-forward[2], 0., forward[0]];
+                let right = scale(right, 1. / length(right));
+                let up = [
+                    right[1] * forward[2] - right[2] * forward[1],
+                    right[2] * forward[0] - right[0] * forward[2],
+                    right[0] * forward[1] - right[1] * forward[0],
+                ];
+
+                let amount = self.settings.pan_speed * self.distance;
+                for i in 0..3 {
+                    self.target[i] += (-dx * right[i] + dy * up[i]) * amount;
+                }
+            }
+        }
+
+        self.cursor = Some((x, y));
+    }
+
+    /// Zooms by the number of lines scrolled. Positive zooms in.
+    pub fn scroll(&mut self, lines: f32) {
+        let zoom = (1. - self.settings.zoom_speed).powf(lines);
+        self.distance = (self.distance * zoom).max(self.settings.min_distance);
+    }
+
+    pub fn eye(&self) -> (f32, f32, f32) {
+        let forward = direction(self.yaw, self.pitch);
+        to_tuple(sub(self.target, scale(forward, self.distance)))
+    }
+
+    pub fn target(&self) -> (f32, f32, f32) {
+        to_tuple(self.target)
+    }
+}
@@ -0,0 +1,252 @@
+// Parsing for the `.cfg` files in `res`. Lines are `field = value` pairs grouped into `[section]`s, and anything
+// after a `#` is ignored. What the sections and fields mean is up to each file.
+
+use std::fmt;
+
+/// Errors from parsing a config file. Lines start at 1.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CfgError {
+    /// The line isn't a `[section]` or of the form `field = value`
+    Syntax {
+        line: usize,
+    },
+    UnknownSection {
+        line: usize,
+        section: String,
+    },
+    UnknownField {
+        line: usize,
+        field: String,
+    },
+    InvalidValue {
+        line: usize,
+        field: String,
+    },
+    MissingField {
+        line: usize,
+        field: String,
+    },
+    /// The value refers to something, such as a material, that doesn't exist
+    UnknownName {
+        line: usize,
+        name: String,
+    },
+    /// The value is a path to a file that couldn't be loaded, for the reason given
+    UnreadableFile {
+        line: usize,
+        path: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for CfgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CfgError::Syntax { line } => {
+                write!(f, "line {}: expected `[section]` or `field = value`", line)
+            }
+            CfgError::UnknownSection { line, section } => {
+                write!(f, "line {}: unknown section `[{}]`", line, section)
+            }
+            CfgError::UnknownField { line, field } => {
+                write!(f, "line {}: unknown field `{}`", line, field)
+            }
+            CfgError::InvalidValue { line, field } => {
+                write!(f, "line {}: invalid value for `{}`", line, field)
+            }
+            CfgError::MissingField { line, field } => {
+                write!(f, "line {}: missing `{}`", line, field)
+            }
+            CfgError::UnknownName { line, name } => {
+                write!(f, "line {}: unknown name `{}`", line, name)
+            }
+            CfgError::UnreadableFile { line, path, reason } => {
+                write!(f, "line {}: couldn't load `{}`: {}", line, path, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CfgError {}
+
+pub struct Section<'a> {
+    /// The line of the `[section]` header
+    pub line: usize,
+    pub name: &'a str,
+    pub fields: Vec<Field<'a>>,
+}
+
+impl<'a> Section<'a> {
+    pub fn unknown(&self) -> CfgError {
+        CfgError::UnknownSection {
+            line: self.line,
+            section: self.name.to_string(),
+        }
+    }
+
+    pub fn missing(&self, field: &str) -> CfgError {
+        CfgError::MissingField {
+            line: self.line,
+            field: field.to_string(),
+        }
+    }
+
+    /// The value of the field, if it's set. Later fields win.
+    pub fn get(&self, name: &str) -> Option<&Field<'a>> {
+        self.fields.iter().rev().find(|field| field.name == name)
+    }
+}
+
+pub struct Field<'a> {
+    pub line: usize,
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+impl<'a> Field<'a> {
+    pub fn unknown(&self) -> CfgError {
+        CfgError::UnknownField {
+            line: self.line,
+            field: self.name.to_string(),
+        }
+    }
+
+    pub fn invalid(&self) -> CfgError {
+        CfgError::InvalidValue {
+            line: self.line,
+            field: self.name.to_string(),
+        }
+    }
+
+    pub fn num(&self) -> Result<f32, CfgError> {
+        parse_num(self.value).ok_or_else(|| self.invalid())
+    }
+
+    /// Parses `x, y, z`.
+    pub fn vec3(&self) -> Result<(f32, f32, f32), CfgError> {
+        let mut components = self
+            .value
+            .split(',')
+            .map(|component| parse_num(component.trim()));
+
+        match (
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+        ) {
+            (Some(Some(x)), Some(Some(y)), Some(Some(z)), None) => Ok((x, y, z)),
+            _ => Err(self.invalid()),
+        }
+    }
+}
+
+/// Splits the text into its sections. Fields before the first section are an error.
+pub fn sections(text: &str) -> Result<Vec<Section<'_>>, CfgError> {
+    let mut sections: Vec<Section> = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            let name = match line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                Some(name) => name.trim(),
+                None => return Err(CfgError::Syntax { line: line_number }),
+            };
+
+            sections.push(Section {
+                line: line_number,
+                name,
+                fields: vec![],
+            });
+
+            continue;
+        }
+
+        let mut sides = line.splitn(2, '=');
+        let field = match (sides.next(), sides.next()) {
+            (Some(name), Some(value)) => Field {
+                line: line_number,
+                name: name.trim(),
+                value: value.trim(),
+            },
+            _ => return Err(CfgError::Syntax { line: line_number }),
+        };
+
+        match sections.last_mut() {
+            Some(section) => section.fields.push(field),
+            None => return Err(field.unknown()),
+        }
+    }
+
+    Ok(sections)
+}
+
+fn parse_num(value: &str) -> Option<f32> {
+    value.parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cfg_sections() {
+        let text = "# A comment\n[first]\na = 1 # Trailing comment\n\nb = x = y\n[ second ]\na = 2\na = 3\n";
+        let sections = sections(text).unwrap();
+
+        assert_eq!(2, sections.len());
+        assert_eq!("first", sections[0].name);
+        assert_eq!(2, sections[0].line);
+        assert_eq!("1", sections[0].get("a").unwrap().value);
+        assert_eq!("x = y", sections[0].get("b").unwrap().value);
+        assert_eq!(5, sections[0].get("b").unwrap().line);
+
+        // Later fields win
+        assert_eq!("second", sections[1].name);
+        assert_eq!("3", sections[1].get("a").unwrap().value);
+        assert!(sections[1].get("b").is_none());
+    }
+
+    #[test]
+    fn cfg_syntax_errors() {
+        assert_eq!(
+            Some(CfgError::Syntax { line: 2 }),
+            sections("[first]\n[second\n").err()
+        );
+        assert_eq!(
+            Some(CfgError::Syntax { line: 2 }),
+            sections("[first]\nno value\n").err()
+        );
+        assert_eq!(
+            Some(CfgError::UnknownField {
+                line: 1,
+                field: "a".to_string()
+            }),
+            sections("a = 1\n[first]\n").err()
+        );
+    }
+
+    #[test]
+    fn cfg_values() {
+        let sections =
+            sections("[values]\nnum = -1.5\nvec = 1, 2 ,3\nnan = NaN\npair = 1, 2\n").unwrap();
+        let field = |name| sections[0].get(name).unwrap();
+
+        assert_eq!(Ok(-1.5), field("num").num());
+        assert_eq!(Ok((1., 2., 3.)), field("vec").vec3());
+        assert!(field("vec").num().is_err());
+        assert_eq!(
+            Err(CfgError::InvalidValue {
+                line: 4,
+                field: "nan".to_string()
+            }),
+            field("nan").num()
+        );
+        assert!(field("pair").vec3().is_err());
+    }
+}
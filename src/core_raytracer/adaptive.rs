@@ -0,0 +1,124 @@
+// Adaptive sampling. Every pixel gets a few samples, after which the remaining per frame budget is
+// spent on the pixels whose estimates are the noisiest.
+
+use super::{
+    debug::TraceStats,
+    num::{Num, INFINITY},
+    vec3::Color,
+};
+use std::cmp::Ordering;
+
+/// Keeps dark pixels from reporting huge relative errors over tiny absolute differences.
+const MIN_LUMINANCE: Num = 0.01;
+
+/// Settings for adaptive sampling.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Samples every pixel gets before any error is estimated. At least 2 are always taken.
+    pub min_samples: u32,
+    /// Max samples a single pixel may take
+    pub max_samples: u32,
+    /// Pixels with a relative error above this receive more samples
+    pub error_threshold: Num,
+    /// Max samples taken across the whole frame, including the first pass.
+    /// Scaled down when only part of the frame is traced.
+    pub frame_budget: u32,
+}
+
+impl AdaptiveSampling {
+    /// The number of samples every pixel starts with.
+    pub fn initial_samples(&self) -> u32 {
+        self.min_samples.max(2)
+    }
+}
+
+/// Running estimate of a pixel's color.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PixelEstimate {
+    /// Sum of all sampled colors
+    pub sum: Color,
+    /// Stats gathered across all samples
+    pub stats: TraceStats,
+    // Welford's online variance of the sampled luminance
+    mean: Num,
+    m2: Num,
+    samples: u32,
+}
+
+impl PixelEstimate {
+    /// An estimate holding a single color that was resolved elsewhere, such as by upscaling.
+    pub fn resolved(color: Color, stats: TraceStats) -> Self {
+        let mut estimate = Self {
+            stats,
+            ..Self::default()
+        };
+        estimate.add(color);
+
+        estimate
+    }
+
+    pub fn add(&mut self, color: Color) {
+        self.sum += color;
+        self.samples += 1;
+
+        let lum = luminance(color);
+        let delta = lum - self.mean;
+        self.mean += delta / self.samples as Num;
+        self.m2 += delta * (lum - self.mean);
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// The standard error of the mean luminance, relative to the mean.
+    pub fn relative_error(&self) -> Num {
+        if self.samples < 2 {
+            return INFINITY;
+        }
+
+        let n = self.samples as Num;
+        let variance = self.m2 / (n - 1.);
+
+        (variance / n).sqrt() / self.mean.max(MIN_LUMINANCE)
+    }
+}
+
+/// Spends what's left of the budget on the noisiest pixels, one sample per pixel per pass.
+/// Pixels without any samples weren't traced this frame and are left alone.
+/// `sample` takes a single sample for the pixel at the given index.
+pub fn refine(
+    estimates: &mut [PixelEstimate],
+    settings: &AdaptiveSampling,
+    budget: u32,
+    mut sample: impl FnMut(usize, &mut PixelEstimate),
+) {
+    let used: u32 = estimates.iter().map(|e| e.samples).sum();
+    let mut remaining = budget.saturating_sub(used);
+
+    while remaining > 0 {
+        let mut noisy: Vec<(usize, Num)> = estimates
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.samples > 0 && e.samples < settings.max_samples)
+            .map(|(i, e)| (i, e.relative_error()))
+            .filter(|(_, error)| *error > settings.error_threshold)
+            .collect();
+
+        if noisy.is_empty() {
+            break;
+        }
+
+        // Worst first, so the budget goes where it matters most if it runs out mid pass
+        noisy.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+        for (i, _) in noisy.into_iter().take(remaining as usize) {
+            sample(i, &mut estimates[i]);
+            remaining -= 1;
+        }
+    }
+}
+
+fn luminance(c: Color) -> Num {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
@@ -1,11 +1,33 @@
 use super::{
     deg_to_rads,
+    num::{Num, PI},
     ray::Ray,
     vec3::{Point3, Vec3},
 };
 
-// Could add focus blur: https://raytracing.github.io/books/RayTracingInOneWeekend.html#defocusblur
+/// How the camera maps the viewport onto rays.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    /// Pinhole camera, using the camera's vertical FOV
+    Perspective,
+    /// Parallel rays. `height` is the height of the view in world units.
+    Orthographic { height: Num },
+    /// Equidistant fisheye. `fov_deg` is the angle covered from the top to the bottom of the view.
+    Fisheye { fov_deg: Num },
+    /// Full 360 degree panorama, with longitude across and latitude up the view
+    Equirectangular,
+}
+
+/// Thin lens settings for depth of field. See https://raytracing.github.io/books/RayTracingInOneWeekend.html#defocusblur
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Focus {
+    /// Distance from the camera to the plane that is in perfect focus
+    pub distance: Num,
+    /// Diameter of the lens. Larger values blur more.
+    pub aperture: Num,
+}
 
+#[derive(Clone)]
 pub struct Camera {
     origin: Point3,
     target: Point3,
@@ -13,12 +35,20 @@ pub struct Camera {
     lower_left_corner: Point3,
     horizontal: Vec3,
     vertical: Vec3,
-    viewport_width: f32,
-    viewport_height: f32,
+    viewport_width: Num,
+    viewport_height: Num,
+    projection: Projection,
+    /// `None` for a pinhole camera, where everything is in focus
+    focus: Option<Focus>,
+
+    // Camera basis. `w` points backwards, away from the target.
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
 }
 
 impl Camera {
-    pub fn new(aspect_ratio: f32, v_fov_deg: f32) -> Self {
+    pub fn new(aspect_ratio: Num, v_fov_deg: Num, projection: Projection) -> Self {
         let theta = deg_to_rads(v_fov_deg);
         let h = (theta / 2.).tan();
         let viewport_height = 2. * h;
@@ -39,8 +69,13 @@ impl Camera {
             vertical,
             viewport_width,
             viewport_height,
+            projection,
+            focus: None,
             target: (0., 0., 1.).into(),
             up: Vec3::unit_y(),
+            u: Vec3::new(1., 0., 0.),
+            v: Vec3::unit_y(),
+            w: Vec3::new(0., 0., 1.),
         };
 
         camera.look_at((0., 0., 0.).into(), (0., 0., 1.).into(), Vec3::unit_y());
@@ -60,6 +95,33 @@ impl Camera {
         self.up
     }
 
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    /// Changes the vertical FOV, keeping the aspect ratio.
+    pub fn set_v_fov(&mut self, v_fov_deg: Num) {
+        let aspect_ratio = self.aspect_ratio();
+
+        self.viewport_height = 2. * (deg_to_rads(v_fov_deg) / 2.).tan();
+        self.viewport_width = aspect_ratio * self.viewport_height;
+
+        self.look_at(self.origin, self.target, self.up);
+    }
+
+    pub fn focus(&self) -> Option<Focus> {
+        self.focus
+    }
+
+    /// Sets the depth of field. Only the perspective projection uses it.
+    pub fn set_focus(&mut self, focus: Option<Focus>) {
+        self.focus = focus;
+    }
+
     pub fn look_at(&mut self, eye: Point3, target: Point3, up: Vec3) {
         let w = (eye - target).unit_vector();
         let u = up.cross(w).unit_vector();
@@ -68,18 +130,133 @@ impl Camera {
         self.target = target;
         self.up = up;
         self.origin = eye;
+        self.u = u;
+        self.v = v;
+        self.w = w;
 
         self.horizontal = self.viewport_width * u;
         self.vertical = self.viewport_height * v;
         self.lower_left_corner = self.origin - self.horizontal / 2. - self.vertical / 2. - w;
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let r = Ray::new(
-            self.origin,
-            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin,
-        );
+    /// Returns the ray through the viewport at `s` across and `t` up, each in `0.0..1.0`.
+    pub fn get_ray(&self, s: Num, t: Num) -> Ray {
+        match self.projection {
+            Projection::Perspective => Ray::new(
+                self.origin,
+                self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin,
+            ),
+            Projection::Orthographic { height } => {
+                let width = height * self.aspect_ratio();
+                let origin = self.origin + (s - 0.5) * width * self.u + (t - 0.5) * height * self.v;
+
+                Ray::new(origin, -self.w)
+            }
+            Projection::Fisheye { fov_deg } => {
+                // Centered so the top and bottom of the view are at a radius of 1
+                let x = (s - 0.5) * 2. * self.aspect_ratio();
+                let y = (t - 0.5) * 2.;
+                let theta = ((x * x + y * y).sqrt() * deg_to_rads(fov_deg) / 2.).min(PI);
+                let phi = y.atan2(x);
+
+                let dir =
+                    theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w;
+
+                Ray::new(self.origin, dir)
+            }
+            Projection::Equirectangular => {
+                let longitude = (s - 0.5) * 2. * PI;
+                let latitude = (t - 0.5) * PI;
+
+                let dir = latitude.cos() * (longitude.sin() * self.u - longitude.cos() * self.w)
+                    + latitude.sin() * self.v;
+
+                Ray::new(self.origin, dir)
+            }
+        }
+    }
+
+    /// Like `get_ray`, but starting from a random point on the lens so that anything off the focus plane is blurred.
+    pub fn get_lens_ray(&self, s: Num, t: Num) -> Ray {
+        let ray = self.get_ray(s, t);
+
+        match (self.projection, self.focus) {
+            (Projection::Perspective, Some(focus)) if focus.aperture > 0. => {
+                // The perspective ray travels a unit distance forward, so this lands on the focus plane
+                let focus_point = ray.origin() + focus.distance * ray.direction();
+                let disk = focus.aperture / 2. * Vec3::random_in_unit_disk();
+                let origin = ray.origin() + disk.x * self.u + disk.y * self.v;
+
+                Ray::new(origin, focus_point - origin)
+            }
+            _ => ray,
+        }
+    }
+
+    /// Projects the point onto the viewport, returning the `s` and `t` that `get_ray` would take to hit it.
+    /// Returns `None` for points the camera can't see.
+    pub fn project(&self, point: Point3) -> Option<(Num, Num)> {
+        let dir = point - self.origin;
+
+        // Direction in camera space, looking down +z
+        let x = dir.dot(self.u);
+        let y = dir.dot(self.v);
+        let z = -dir.dot(self.w);
+
+        match self.projection {
+            Projection::Perspective => {
+                let forward = self.lower_left_corner + self.horizontal / 2. + self.vertical / 2.
+                    - self.origin;
+                let depth = dir.dot(forward);
+                if depth <= 0. {
+                    return None;
+                }
+
+                // Where the direction crosses the viewport, which is a unit distance in front of the camera
+                let on_viewport = self.origin + dir / depth - self.lower_left_corner;
+                let s = on_viewport.dot(self.horizontal) / self.horizontal.len_sqrd();
+                let t = on_viewport.dot(self.vertical) / self.vertical.len_sqrd();
+
+                Some((s, t))
+            }
+            Projection::Orthographic { height } => {
+                if z < 0. {
+                    return None;
+                }
+
+                let width = height * self.aspect_ratio();
+                Some((x / width + 0.5, y / height + 0.5))
+            }
+            Projection::Fisheye { fov_deg } => {
+                let len = dir.len();
+                if len <= 0. {
+                    return None;
+                }
+
+                let theta = (z / len).max(-1.).min(1.).acos();
+                let phi = y.atan2(x);
+                let r = theta / (deg_to_rads(fov_deg) / 2.);
+
+                let s = r * phi.cos() / (2. * self.aspect_ratio()) + 0.5;
+                let t = r * phi.sin() / 2. + 0.5;
+
+                Some((s, t))
+            }
+            Projection::Equirectangular => {
+                let len = dir.len();
+                if len <= 0. {
+                    return None;
+                }
+
+                let longitude = x.atan2(z);
+                let latitude = (y / len).max(-1.).min(1.).asin();
+
+                Some((longitude / (2. * PI) + 0.5, latitude / PI + 0.5))
+            }
+        }
+    }
 
-        r
+    fn aspect_ratio(&self) -> Num {
+        self.viewport_width / self.viewport_height
     }
 }
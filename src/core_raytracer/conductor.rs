@@ -0,0 +1,64 @@
+use super::{num::Num, vec3::Color};
+
+/// Common metals, with RGB complex indices of refraction sampled at roughly 650nm, 550nm and 450nm.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Conductor {
+    Gold,
+    Copper,
+    Silver,
+    Aluminium,
+}
+
+impl Conductor {
+    /// The real part of the index of refraction
+    pub fn eta(&self) -> Color {
+        match self {
+            Conductor::Gold => Color::new(0.143, 0.374, 1.442),
+            Conductor::Copper => Color::new(0.200, 0.924, 1.102),
+            Conductor::Silver => Color::new(0.155, 0.117, 0.138),
+            Conductor::Aluminium => Color::new(1.657, 0.880, 0.521),
+        }
+    }
+
+    /// The imaginary part of the index of refraction, the extinction coefficient
+    pub fn k(&self) -> Color {
+        match self {
+            Conductor::Gold => Color::new(3.983, 2.385, 1.603),
+            Conductor::Copper => Color::new(3.912, 2.452, 2.142),
+            Conductor::Silver => Color::new(4.828, 3.122, 2.147),
+            Conductor::Aluminium => Color::new(9.224, 6.270, 4.837),
+        }
+    }
+}
+
+/// Exact Fresnel reflectance of a conductor for unpolarized light, per color channel.
+/// `cos_theta` is the cosine of the angle between the incident direction and the normal.
+pub fn fresnel_conductor(cos_theta: Num, eta: Color, k: Color) -> Color {
+    let cos_theta = cos_theta.max(0.).min(1.);
+
+    Color::new(
+        fresnel_conductor_channel(cos_theta, eta.x, k.x),
+        fresnel_conductor_channel(cos_theta, eta.y, k.y),
+        fresnel_conductor_channel(cos_theta, eta.z, k.z),
+    )
+}
+
+fn fresnel_conductor_channel(cos_theta: Num, eta: Num, k: Num) -> Num {
+    let cos2 = cos_theta * cos_theta;
+    let sin2 = 1. - cos2;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4. * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.).sqrt();
+    let t2 = 2. * cos_theta * a;
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+
+    0.5 * (rp + rs)
+}
@@ -0,0 +1,142 @@
+use super::{
+    hittable::{Hittable, World},
+    num::Num,
+    ray::Ray,
+    vec3::Color,
+    MAX_DRAW, MIN_DRAW,
+};
+
+/// Primary hits further away than this are drawn as black in the depth view.
+const MAX_DEBUG_DEPTH: Num = 20.;
+
+/// The different debug visualizations the raytracer can output instead of the shaded scene.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DebugMode {
+    /// Regular shaded output
+    Off,
+    /// Surface normal of the primary hit
+    Normals,
+    /// Distance to the primary hit. Brighter is closer.
+    Depth,
+    /// Surface UVs of the primary hit
+    Uvs,
+    /// Heatmap of the number of intersection tests performed for a pixel. Each set of spheres tested at once counts
+    /// as one.
+    TraversalCost,
+    /// Heatmap of the number of bounces a pixel's paths took
+    Bounces,
+    /// Heatmap of the number of samples taken for a pixel
+    SampleCount,
+    /// False color based on the material of the primary hit
+    MaterialId,
+}
+
+impl DebugMode {
+    /// Whether the mode only needs the primary hit, rather than a full path trace.
+    pub fn is_surface_view(&self) -> bool {
+        match self {
+            DebugMode::Normals | DebugMode::Depth | DebugMode::Uvs | DebugMode::MaterialId => true,
+            DebugMode::Off
+            | DebugMode::TraversalCost
+            | DebugMode::Bounces
+            | DebugMode::SampleCount => false,
+        }
+    }
+
+    /// Whether the mode is built from the `TraceStats` of a pixel.
+    pub fn is_heatmap(&self) -> bool {
+        match self {
+            DebugMode::TraversalCost | DebugMode::Bounces | DebugMode::SampleCount => true,
+            _ => false,
+        }
+    }
+}
+
+/// Counters gathered while tracing a single pixel.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TraceStats {
+    /// Number of intersection tests, counting each set of spheres tested at once as one
+    pub tests: u32,
+    /// Number of bounces
+    pub bounces: u32,
+    /// Number of samples
+    pub samples: u32,
+}
+
+/// Returns the debug color for the primary hit of the ray. Misses are black.
+pub fn surface_color(mode: DebugMode, ray: &Ray, world: &World) -> Color {
+    let rec = match world.hit(ray, MIN_DRAW, MAX_DRAW) {
+        Some(rec) => rec,
+        None => return Color::default(),
+    };
+
+    match mode {
+        DebugMode::Normals => 0.5 * (rec.normal + Color::new(1., 1., 1.)),
+        DebugMode::Depth => {
+            let depth = rec.t * ray.direction().len();
+            let d = 1. - (depth / MAX_DEBUG_DEPTH).min(1.);
+            Color::new(d, d, d)
+        }
+        DebugMode::Uvs => Color::new(rec.u, rec.v, 0.),
+        DebugMode::MaterialId => false_color(rec.material.id()),
+        _ => Color::default(),
+    }
+}
+
+/// Returns the heatmap color for the gathered stats.
+/// `max_tests` and `max_bounces` are the per sample upper bounds; `max_samples` is the per pixel upper bound.
+pub fn stats_color(
+    mode: DebugMode,
+    stats: &TraceStats,
+    max_tests: u32,
+    max_bounces: u32,
+    max_samples: u32,
+) -> Color {
+    let samples = stats.samples.max(1) as Num;
+
+    let heat = match mode {
+        DebugMode::TraversalCost => log_scale(stats.tests as Num / samples, max_tests as Num),
+        DebugMode::Bounces => log_scale(stats.bounces as Num / samples, max_bounces as Num),
+        DebugMode::SampleCount => stats.samples as Num / max_samples.max(1) as Num,
+        _ => 0.,
+    };
+
+    heatmap(heat)
+}
+
+/// Maps the value onto `0.0..1.0` logarithmically, so small counts are still distinguishable.
+fn log_scale(value: Num, max: Num) -> Num {
+    if max <= 0. {
+        return 0.;
+    }
+
+    ((1. + value).ln() / (1. + max).ln()).min(1.)
+}
+
+/// Maps a normalized `0.0..1.0` value onto a blue -> green -> red gradient.
+pub fn heatmap(value: Num) -> Color {
+    let v = value.max(0.).min(1.);
+
+    if v < 0.5 {
+        let t = v * 2.;
+        Color::new(0., t, 1. - t)
+    } else {
+        let t = (v - 0.5) * 2.;
+        Color::new(t, 1. - t, 0.)
+    }
+}
+
+/// Generates a stable, saturated color for the given id.
+fn false_color(id: u32) -> Color {
+    // Scramble the bits so sequential ids don't end up with similar colors
+    let mut h = id.wrapping_mul(0x9E37_79B9);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85EB_CA6B);
+    h ^= h >> 13;
+
+    let r = (h & 0xFF) as Num / 255.;
+    let g = ((h >> 8) & 0xFF) as Num / 255.;
+    let b = ((h >> 16) & 0xFF) as Num / 255.;
+
+    Color::new(0.2 + 0.8 * r, 0.2 + 0.8 * g, 0.2 + 0.8 * b)
+}
@@ -0,0 +1,124 @@
+use super::{
+    frame::{around_axis, closest, solve_quadratic, Frame, LocalHit},
+    *,
+};
+
+/// A cylinder with rounded ends, running between two points.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Capsule {
+    frame: Frame,
+    /// Distance between the centers of the ends
+    length: Num,
+    radius: Num,
+    material: Material,
+}
+
+impl Capsule {
+    /// Creates the capsule. If the points are the same it's a sphere, and its UVs run up the Y axis.
+    pub fn new(a: Point3, b: Point3, radius: Num, material: Material) -> Self {
+        let length = (b - a).len();
+        let axis = if length > 0. { b - a } else { Vec3::unit_y() };
+
+        Self {
+            frame: Frame::new(a, axis),
+            length,
+            radius,
+            material,
+        }
+    }
+
+    /// The hit for the point at `t`, on the surface around the point on the axis at `center_y`.
+    /// `v` runs from the tip of the first end to the tip of the second.
+    fn hit_at(&self, ray: &Ray, t: Num, center_y: Num) -> LocalHit {
+        let p = ray.at(t);
+
+        LocalHit {
+            t,
+            normal: p - Vec3::new(0., center_y, 0.),
+            uv: (
+                around_axis(p),
+                (p.y + self.radius) / (self.length + 2. * self.radius),
+            ),
+        }
+    }
+
+    /// Where the ray crosses the sphere at the end centered at `center_y`, on the side facing away from the middle.
+    fn end(&self, ray: &Ray, center_y: Num, below: bool) -> [Option<LocalHit>; 2] {
+        let oc = ray.origin() - Vec3::new(0., center_y, 0.);
+        let d = ray.direction();
+        let roots = solve_quadratic(
+            d.len_sqrd(),
+            2. * oc.dot(d),
+            oc.len_sqrd() - self.radius * self.radius,
+        );
+
+        let hit = |t: Num| {
+            let y = ray.at(t).y;
+            if (below && y <= center_y) || (!below && y >= center_y) {
+                Some(self.hit_at(ray, t, center_y))
+            } else {
+                None
+            }
+        };
+
+        match roots {
+            Some((t0, t1)) => [hit(t0), hit(t1)],
+            None => [None, None],
+        }
+    }
+}
+
+impl Hittable for Capsule {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        let local = self.frame.local_ray(ray);
+        let (o, d) = (local.origin(), local.direction());
+
+        let a = d.x * d.x + d.z * d.z;
+        let b = 2. * (o.x * d.x + o.z * d.z);
+        let c = o.x * o.x + o.z * o.z - self.radius * self.radius;
+        let side = |t: Num| {
+            let y = local.at(t).y;
+            if 0. <= y && y <= self.length {
+                Some(self.hit_at(&local, t, y))
+            } else {
+                None
+            }
+        };
+        let [side0, side1] = match solve_quadratic(a, b, c) {
+            Some((t0, t1)) => [side(t0), side(t1)],
+            None => [None, None],
+        };
+
+        let [bottom0, bottom1] = self.end(&local, 0., true);
+        let [top0, top1] = self.end(&local, self.length, false);
+
+        let hit = closest([side0, side1, bottom0, bottom1, top0, top1], t_min, t_max)?;
+
+        Some(self.frame.hit_record(ray, hit, self.material))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capsule_with_one_point_is_sphere() {
+        let center = Point3::new(1., 2., 3.);
+        let capsule = Capsule::new(center, center, 0.5, test_material());
+
+        for direction in [
+            Vec3::new(1., 0., 0.),
+            Vec3::new(0., -1., 0.),
+            Vec3::new(0., 0.6, 0.8),
+        ]
+        .iter()
+        {
+            let ray = Ray::new(center - 2. * *direction, *direction);
+            let rec = capsule.hit(&ray, 0.001, INFINITY).unwrap();
+            assert_close(1.5, rec.t);
+            assert!(rec.front_face);
+            assert_close(-1., rec.normal.dot(*direction));
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use super::{
+    frame::{around_axis, closest, disk, solve_quadratic, Frame, LocalHit},
+    *,
+};
+
+/// A cone standing on its base, with its tip along the frame's axis.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cone {
+    frame: Frame,
+    /// Radius of the base
+    radius: Num,
+    height: Num,
+    /// Whether the base is closed
+    capped: bool,
+    material: Material,
+}
+
+impl Cone {
+    pub fn new(frame: Frame, radius: Num, height: Num, capped: bool, material: Material) -> Self {
+        Self {
+            frame,
+            radius,
+            height,
+            capped,
+            material,
+        }
+    }
+
+    fn side(&self, ray: &Ray, t: Num) -> Option<LocalHit> {
+        let p = ray.at(t);
+        if p.y < 0. || p.y > self.height {
+            return None;
+        }
+
+        // The gradient of `x² + z² - (k (h - y))²`
+        let k = self.radius / self.height;
+        Some(LocalHit {
+            t,
+            normal: Vec3::new(p.x, k * k * (self.height - p.y), p.z),
+            uv: (around_axis(p), p.y / self.height),
+        })
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        let local = self.frame.local_ray(ray);
+        let (o, d) = (local.origin(), local.direction());
+
+        // The radius shrinks by `k` per unit of height, reaching zero at the tip
+        let k2 = (self.radius / self.height).powi(2);
+        let h = self.height - o.y;
+        let a = d.x * d.x + d.z * d.z - k2 * d.y * d.y;
+        let b = 2. * (o.x * d.x + o.z * d.z + k2 * h * d.y);
+        let c = o.x * o.x + o.z * o.z - k2 * h * h;
+        let (t0, t1) = solve_quadratic(a, b, c).unwrap_or((-INFINITY, -INFINITY));
+
+        let base = if self.capped {
+            disk(&local, 0., self.radius, false)
+        } else {
+            None
+        };
+
+        let hit = closest(
+            [self.side(&local, t0), self.side(&local, t1), base],
+            t_min,
+            t_max,
+        )?;
+
+        Some(self.frame.hit_record(ray, hit, self.material))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Base radius 1 and height 2, standing on the origin.
+    fn cone() -> Cone {
+        let frame = Frame::new(Point3::default(), Vec3::unit_y());
+        Cone::new(frame, 1., 2., true, test_material())
+    }
+
+    #[test]
+    fn cone_hit_side() {
+        // Halfway up, the radius is 0.5
+        let ray = Ray::new(Point3::new(0., 1., 3.), Vec3::new(0., 0., -1.));
+        let rec = cone().hit(&ray, 0.001, INFINITY).unwrap();
+
+        assert_close(2.5, rec.t);
+        assert!(rec.front_face);
+        assert_vec_close(Vec3::new(0., 0.5, 1.).unit_vector(), rec.normal);
+        assert_close(0.25, rec.u);
+        assert_close(0.5, rec.v);
+    }
+
+    #[test]
+    fn cone_hit_base() {
+        let ray = Ray::new(Point3::new(0., -2., 0.5), Vec3::new(0., 1., 0.));
+        let rec = cone().hit(&ray, 0.001, INFINITY).unwrap();
+
+        assert_close(2., rec.t);
+        assert!(rec.front_face);
+        assert_vec_close(Vec3::new(0., -1., 0.), rec.normal);
+        assert_close(0.5, rec.u);
+        assert_close(0.75, rec.v);
+    }
+
+    #[test]
+    fn cone_seen_from_inside() {
+        let ray = Ray::new(Point3::new(0., 0.5, 0.), Vec3::new(0., 0., -1.));
+        let rec = cone().hit(&ray, 0.001, INFINITY).unwrap();
+
+        assert_close(0.75, rec.t);
+        assert!(!rec.front_face);
+        assert_vec_close(-Vec3::new(0., 0.5, -1.).unit_vector(), rec.normal);
+        assert_close(0.75, rec.u);
+
+        // Past the tip, where the mirrored half of the surface mustn't count
+        let ray = Ray::new(Point3::new(0., 3., 3.), Vec3::new(0., 0., -1.));
+        assert!(cone().hit(&ray, 0.001, INFINITY).is_none());
+    }
+}
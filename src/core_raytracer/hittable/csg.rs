@@ -0,0 +1,199 @@
+// Constructive solid geometry. The surfaces each child crosses along a ray split it into the intervals the ray is
+// inside that child, and the node combines the intervals of its children. Where the result starts or stops being
+// inside is where the ray crosses the combined surface.
+
+use super::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CsgOp {
+    /// Inside either child
+    Union,
+    /// Inside both children
+    Intersection,
+    /// Inside the first child but not the second
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(&self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOp::Union => in_a || in_b,
+            CsgOp::Intersection => in_a && in_b,
+            CsgOp::Difference => in_a && !in_b,
+        }
+    }
+}
+
+/// Combines two closed shapes. Nodes can be nested to build up more complex shapes.
+pub struct Csg {
+    op: CsgOp,
+    a: Box<dyn Hittable>,
+    b: Box<dyn Hittable>,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Self {
+        Self { op, a, b }
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        self.crossings(ray, t_min, t_max).into_iter().next()
+    }
+
+    fn crossings(&self, ray: &Ray, t_min: Num, t_max: Num) -> Vec<HitRecord> {
+        // Children are searched past `t_max`, as a ray that's inside one is only known to be by where it leaves
+        let a = self.a.crossings(ray, t_min, INFINITY);
+        let b = self.b.crossings(ray, t_min, INFINITY);
+
+        let mut in_a = a.first().map_or(false, |rec| !rec.front_face);
+        let mut in_b = b.first().map_or(false, |rec| !rec.front_face);
+        let mut inside = self.op.inside(in_a, in_b);
+
+        let mut a = a.into_iter().peekable();
+        let mut b = b.into_iter().peekable();
+        let mut crossings = vec![];
+
+        loop {
+            let from_a = match (a.peek(), b.peek()) {
+                (Some(rec_a), Some(rec_b)) => rec_a.t <= rec_b.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+
+            let rec = if from_a {
+                let rec = a.next().unwrap();
+                in_a = rec.front_face;
+                rec
+            } else {
+                let rec = b.next().unwrap();
+                in_b = rec.front_face;
+                rec
+            };
+
+            if rec.t > t_max {
+                break;
+            }
+
+            let now_inside = self.op.inside(in_a, in_b);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+
+            // Where the second child is cut away, its inside becomes the outside of the result
+            let outward_normal = if rec.front_face {
+                rec.normal
+            } else {
+                -rec.normal
+            };
+            let outward_normal = if !from_a && self.op == CsgOp::Difference {
+                -outward_normal
+            } else {
+                outward_normal
+            };
+
+            crossings.push(HitRecord {
+                normal: if inside {
+                    outward_normal
+                } else {
+                    -outward_normal
+                },
+                front_face: inside,
+                ..rec
+            });
+        }
+
+        crossings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sphere::Sphere, *};
+
+    /// Unit spheres centered on `x = 0` and `x = 1`, overlapping between them.
+    fn overlapping(op: CsgOp) -> Csg {
+        let a = Sphere::new(Point3::new(0., 0., 0.), 1., test_material());
+        let b = Sphere::new(Point3::new(1., 0., 0.), 1., test_material());
+
+        Csg::new(op, Box::new(a), Box::new(b))
+    }
+
+    /// Crossings along the X axis, as `(t, front_face, normal.x)`.
+    fn crossings(csg: &Csg, origin_x: Num, direction_x: Num) -> Vec<(Num, bool, Num)> {
+        let ray = Ray::new(
+            Point3::new(origin_x, 0., 0.),
+            Vec3::new(direction_x, 0., 0.),
+        );
+
+        csg.crossings(&ray, 0.001, INFINITY)
+            .iter()
+            .map(|rec| (rec.t, rec.front_face, rec.normal.x))
+            .collect()
+    }
+
+    fn assert_crossings(expected: &[(Num, bool, Num)], actual: &[(Num, bool, Num)]) {
+        assert_eq!(expected.len(), actual.len(), "{:?}", actual);
+        for (expected, actual) in expected.iter().zip(actual.iter()) {
+            assert_close(expected.0, actual.0);
+            assert_eq!(expected.1, actual.1);
+            assert_close(expected.2, actual.2);
+        }
+    }
+
+    #[test]
+    fn csg_union() {
+        let union = overlapping(CsgOp::Union);
+
+        // Enters the first sphere at -1 and leaves the second at 2
+        assert_crossings(
+            &[(2., true, -1.), (5., false, -1.)],
+            &crossings(&union, -3., 1.),
+        );
+    }
+
+    #[test]
+    fn csg_intersection() {
+        let intersection = overlapping(CsgOp::Intersection);
+
+        // Enters the second sphere at 0 and leaves the first at 1
+        assert_crossings(
+            &[(3., true, -1.), (4., false, -1.)],
+            &crossings(&intersection, -3., 1.),
+        );
+    }
+
+    #[test]
+    fn csg_difference() {
+        let difference = overlapping(CsgOp::Difference);
+
+        // Leaves through the cut at 0, where the ray enters the second sphere
+        assert_crossings(
+            &[(2., true, -1.), (3., false, -1.)],
+            &crossings(&difference, -3., 1.),
+        );
+
+        // Enters through the cut at 0, where the ray leaves the second sphere. The normal faces the ray, so
+        // the outward normal points into the part that was cut away, opposite the second sphere's.
+        assert_crossings(
+            &[(3., true, 1.), (4., false, 1.)],
+            &crossings(&difference, 3., -1.),
+        );
+
+        // Starting inside, the first crossing is on the way out
+        assert_crossings(&[(0.5, false, -1.)], &crossings(&difference, -0.5, 1.));
+    }
+
+    #[test]
+    fn csg_hit_is_first_crossing() {
+        let difference = overlapping(CsgOp::Difference);
+        let ray = Ray::new(Point3::new(3., 0., 0.), Vec3::new(-1., 0., 0.));
+
+        let rec = difference.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(3., rec.t);
+        assert!(difference.hit(&ray, 0.001, 2.).is_none());
+    }
+}
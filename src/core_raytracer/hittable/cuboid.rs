@@ -0,0 +1,139 @@
+use super::*;
+
+/// An axis aligned box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cuboid {
+    min: Point3,
+    max: Point3,
+    material: Material,
+}
+
+impl Cuboid {
+    pub fn new(min: Point3, max: Point3, material: Material) -> Self {
+        Self { min, max, material }
+    }
+
+    /// The outward normal and UVs for a point on the surface. UVs run across each face.
+    fn surface(&self, p: Point3) -> (Vec3, (Num, Num)) {
+        let center = 0.5 * (self.min + self.max);
+        let size = self.max - self.min;
+        let local = p - center;
+
+        // The face the point is on is the one it's relatively furthest towards
+        let scaled = [local.x / size.x, local.y / size.y, local.z / size.z];
+        let axis = if scaled[0].abs() >= scaled[1].abs() && scaled[0].abs() >= scaled[2].abs() {
+            0
+        } else if scaled[1].abs() >= scaled[2].abs() {
+            1
+        } else {
+            2
+        };
+
+        let sign = if scaled[axis] < 0. { -1. } else { 1. };
+        let u = |i: usize| scaled[i] + 0.5;
+        match axis {
+            0 => (Vec3::new(sign, 0., 0.), (u(2), u(1))),
+            1 => (Vec3::new(0., sign, 0.), (u(0), u(2))),
+            _ => (Vec3::new(0., 0., sign), (u(0), u(1))),
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+
+        // Slab test, narrowing the range the ray is inside all three pairs of planes
+        let mut near = -INFINITY;
+        let mut far = INFINITY;
+        let axes = [
+            (origin.x, direction.x, self.min.x, self.max.x),
+            (origin.y, direction.y, self.min.y, self.max.y),
+            (origin.z, direction.z, self.min.z, self.max.z),
+        ];
+        for (o, d, min, max) in axes.iter() {
+            let inv = 1. / d;
+            let (t0, t1) = ((min - o) * inv, (max - o) * inv);
+            let (t0, t1) = if inv < 0. { (t1, t0) } else { (t0, t1) };
+
+            near = near.max(t0);
+            far = far.min(t1);
+        }
+
+        if near > far {
+            return None;
+        }
+
+        let t = if t_min <= near && near <= t_max {
+            near
+        } else if t_min <= far && far <= t_max {
+            far
+        } else {
+            return None;
+        };
+
+        let point = ray.at(t);
+        let (outward_normal, uv) = self.surface(point);
+
+        Some(HitRecord::new(
+            point,
+            ray,
+            outward_normal,
+            t,
+            uv,
+            self.material,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube() -> Cuboid {
+        Cuboid::new(
+            Point3::new(-1., -1., -1.),
+            Point3::new(1., 1., 1.),
+            test_material(),
+        )
+    }
+
+    #[test]
+    fn cuboid_hit_faces() {
+        let cube = cube();
+
+        let ray = Ray::new(Point3::new(0.5, 3., 0.2), Vec3::new(0., -1., 0.));
+        let rec = cube.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(2., rec.t);
+        assert!(rec.front_face);
+        assert_close(1., rec.normal.y);
+        assert_close(0.75, rec.u);
+        assert_close(0.6, rec.v);
+
+        let ray = Ray::new(Point3::new(0., 0., 3.), Vec3::new(1., 0., 0.));
+        assert!(cube.hit(&ray, 0.001, INFINITY).is_none());
+    }
+
+    #[test]
+    fn cuboid_is_closed() {
+        let cube = cube();
+
+        // Every ray through it enters once and leaves once
+        let ray = Ray::new(Point3::new(-3., 0.5, 0.5), Vec3::new(1., 0.2, -0.1));
+        let crossings = cube.crossings(&ray, 0.001, INFINITY);
+        assert_eq!(2, crossings.len());
+        assert!(crossings[0].front_face);
+        assert!(!crossings[1].front_face);
+        assert_close(-1., crossings[0].normal.x);
+        assert_close(-1., crossings[1].normal.y);
+
+        // From inside, the only crossing is on the way out
+        let ray = Ray::new(Point3::default(), Vec3::new(0., 0., -1.));
+        let crossings = cube.crossings(&ray, 0.001, INFINITY);
+        assert_eq!(1, crossings.len());
+        assert_close(1., crossings[0].t);
+        assert!(!crossings[0].front_face);
+        assert_close(1., crossings[0].normal.z);
+    }
+}
@@ -0,0 +1,148 @@
+use super::{
+    frame::{around_axis, closest, disk, solve_quadratic, Frame, LocalHit},
+    *,
+};
+
+/// A cylinder standing on its base, along the frame's axis.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cylinder {
+    frame: Frame,
+    radius: Num,
+    height: Num,
+    /// Whether the ends are closed. Open cylinders are tubes.
+    capped: bool,
+    material: Material,
+}
+
+impl Cylinder {
+    pub fn new(frame: Frame, radius: Num, height: Num, capped: bool, material: Material) -> Self {
+        Self {
+            frame,
+            radius,
+            height,
+            capped,
+            material,
+        }
+    }
+
+    fn side(&self, ray: &Ray, t: Num) -> Option<LocalHit> {
+        let p = ray.at(t);
+        if p.y < 0. || p.y > self.height {
+            return None;
+        }
+
+        Some(LocalHit {
+            t,
+            normal: Vec3::new(p.x, 0., p.z),
+            uv: (around_axis(p), p.y / self.height),
+        })
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        let local = self.frame.local_ray(ray);
+        let (o, d) = (local.origin(), local.direction());
+
+        let a = d.x * d.x + d.z * d.z;
+        let b = 2. * (o.x * d.x + o.z * d.z);
+        let c = o.x * o.x + o.z * o.z - self.radius * self.radius;
+        let (t0, t1) = solve_quadratic(a, b, c).unwrap_or((-INFINITY, -INFINITY));
+
+        let (bottom, top) = if self.capped {
+            (
+                disk(&local, 0., self.radius, false),
+                disk(&local, self.height, self.radius, true),
+            )
+        } else {
+            (None, None)
+        };
+
+        let hit = closest(
+            [self.side(&local, t0), self.side(&local, t1), bottom, top],
+            t_min,
+            t_max,
+        )?;
+
+        Some(self.frame.hit_record(ray, hit, self.material))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Radius 1 and height 2, standing on the origin.
+    fn cylinder(capped: bool) -> Cylinder {
+        let frame = Frame::new(Point3::default(), Vec3::unit_y());
+        Cylinder::new(frame, 1., 2., capped, test_material())
+    }
+
+    #[test]
+    fn cylinder_hit_side() {
+        let ray = Ray::new(Point3::new(0., 1., 3.), Vec3::new(0., 0., -1.));
+        let rec = cylinder(true).hit(&ray, 0.001, INFINITY).unwrap();
+
+        assert_close(2., rec.t);
+        assert!(rec.front_face);
+        assert_vec_close(Vec3::new(0., 0., 1.), rec.normal);
+        assert_close(0.25, rec.u);
+        assert_close(0.5, rec.v);
+    }
+
+    #[test]
+    fn cylinder_hit_caps() {
+        let cylinder = cylinder(true);
+
+        let ray = Ray::new(Point3::new(0.5, 5., 0.), Vec3::new(0., -1., 0.));
+        let rec = cylinder.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(3., rec.t);
+        assert!(rec.front_face);
+        assert_vec_close(Vec3::new(0., 1., 0.), rec.normal);
+        assert_close(0.75, rec.u);
+        assert_close(0.5, rec.v);
+
+        let ray = Ray::new(Point3::new(0., -3., -0.5), Vec3::new(0., 1., 0.));
+        let rec = cylinder.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(3., rec.t);
+        assert!(rec.front_face);
+        assert_vec_close(Vec3::new(0., -1., 0.), rec.normal);
+        assert_close(0.5, rec.u);
+        assert_close(0.25, rec.v);
+    }
+
+    #[test]
+    fn cylinder_open_seen_from_inside() {
+        let cylinder = cylinder(false);
+
+        // In through the open top, and onto the inside of the wall
+        let ray = Ray::new(Point3::new(0., 4., 0.), Vec3::new(0.3, -1., 0.));
+        let rec = cylinder.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(10. / 3., rec.t);
+        assert!(!rec.front_face);
+        assert_vec_close(Vec3::new(-1., 0., 0.), rec.normal);
+        assert_close(0.5, rec.u);
+        assert_close(1. / 3., rec.v);
+
+        // Straight through without any ends
+        let ray = Ray::new(Point3::new(0.5, 4., 0.), Vec3::new(0., -1., 0.));
+        assert!(cylinder.hit(&ray, 0.001, INFINITY).is_none());
+    }
+
+    #[test]
+    fn cylinder_along_any_axis() {
+        let frame = Frame::new(Point3::new(1., 0., 0.), Vec3::new(1., 0., 0.));
+        let cylinder = Cylinder::new(frame, 1., 2., true, test_material());
+
+        let ray = Ray::new(Point3::new(2., 3., 0.), Vec3::new(0., -1., 0.));
+        let rec = cylinder.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(2., rec.t);
+        assert_vec_close(Vec3::new(0., 1., 0.), rec.normal);
+        assert_close(0.5, rec.v);
+
+        let ray = Ray::new(Point3::new(6., 0.5, 0.), Vec3::new(-1., 0., 0.));
+        let rec = cylinder.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(3., rec.t);
+        assert_vec_close(Vec3::new(1., 0., 0.), rec.normal);
+    }
+}
@@ -0,0 +1,186 @@
+// Shared pieces for shapes built around an axis. Each shape is intersected in its own local space, where the axis is
+// +Y and the base is at the origin, so it can be placed and oriented anywhere.
+
+use super::*;
+use crate::core_raytracer::onb::Onb;
+
+/// Where a shape is, and which way its axis points.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frame {
+    origin: Point3,
+    basis: Onb,
+}
+
+/// A surface the ray crosses, in local space.
+pub(super) struct LocalHit {
+    pub t: Num,
+    /// Points out of the shape. Doesn't have to be normalized.
+    pub normal: Vec3,
+    pub uv: (Num, Num),
+}
+
+impl Frame {
+    pub fn new(origin: Point3, axis: Vec3) -> Self {
+        // Local X is world X, or Y for axes close to X, made perpendicular to the axis. Upright frames line up with
+        // the world, so `around_axis` matches spheres for them.
+        let w = axis.unit_vector();
+        let reference = if w.x.abs() > 0.9 {
+            Vec3::unit_y()
+        } else {
+            Vec3::new(1., 0., 0.)
+        };
+        let u = (reference - reference.dot(w) * w).unit_vector();
+        let v = u.cross(w);
+
+        Self {
+            origin,
+            basis: Onb { u, v, w },
+        }
+    }
+
+    /// The ray in local space. The basis is orthonormal, so distances along it are unchanged.
+    pub(super) fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.to_local(ray.origin() - self.origin),
+            self.to_local(ray.direction()),
+        )
+    }
+
+    /// Creates the hit record for a local hit.
+    pub(super) fn hit_record(&self, ray: &Ray, hit: LocalHit, material: Material) -> HitRecord {
+        let n = hit.normal;
+        let outward_normal =
+            (n.x * self.basis.u + n.y * self.basis.w + n.z * self.basis.v).unit_vector();
+
+        HitRecord::new(ray.at(hit.t), ray, outward_normal, hit.t, hit.uv, material)
+    }
+
+    fn to_local(&self, a: Vec3) -> Vec3 {
+        Vec3::new(
+            a.dot(self.basis.u),
+            a.dot(self.basis.w),
+            a.dot(self.basis.v),
+        )
+    }
+}
+
+/// The closest hit in `t_min..t_max`.
+pub(super) fn closest<I: IntoIterator<Item = Option<LocalHit>>>(
+    hits: I,
+    t_min: Num,
+    t_max: Num,
+) -> Option<LocalHit> {
+    hits.into_iter()
+        .flatten()
+        .filter(|hit| t_min <= hit.t && hit.t <= t_max)
+        .min_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+/// The real roots of `a t² + b t + c`, smallest first. A linear equation's root is returned twice.
+pub(super) fn solve_quadratic(a: Num, b: Num, c: Num) -> Option<(Num, Num)> {
+    if a == 0. {
+        return if b == 0. {
+            None
+        } else {
+            Some((-c / b, -c / b))
+        };
+    }
+
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return None;
+    }
+
+    // Avoids cancellation between `b` and the square root
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (t0, t1) = if q == 0. { (0., 0.) } else { (q / a, c / q) };
+
+    Some((t0.min(t1), t0.max(t1)))
+}
+
+/// Where the ray crosses the disk of the given radius around the axis, at `height`. `up` is whether the disk faces +Y.
+pub(super) fn disk(ray: &Ray, height: Num, radius: Num, up: bool) -> Option<LocalHit> {
+    let (o, d) = (ray.origin(), ray.direction());
+    if d.y == 0. {
+        return None;
+    }
+
+    let t = (height - o.y) / d.y;
+    let (x, z) = (o.x + t * d.x, o.z + t * d.z);
+    if x * x + z * z > radius * radius {
+        return None;
+    }
+
+    Some(LocalHit {
+        t,
+        normal: Vec3::new(0., if up { 1. } else { -1. }, 0.),
+        uv: (0.5 + 0.5 * x / radius, 0.5 + 0.5 * z / radius),
+    })
+}
+
+/// How far around the axis the point is, in `0.0..1.0`. Matches the `u` of spheres.
+pub(super) fn around_axis(p: Point3) -> Num {
+    ((-p.z).atan2(p.x) + PI) / (2. * PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(expected: (Num, Num), actual: Option<(Num, Num)>) {
+        let (t0, t1) = actual.unwrap();
+        assert_close(expected.0, t0);
+        assert_close(expected.1, t1);
+    }
+
+    #[test]
+    fn upright_frame_matches_world() {
+        let frame = Frame::new(Point3::new(1., 2., 3.), Vec3::new(0., 2., 0.));
+        let local = frame.local_ray(&Ray::new(Point3::new(2., 4., 6.), Vec3::new(1., 2., 3.)));
+
+        assert_vec_close(Vec3::new(1., 2., 3.), local.origin());
+        assert_vec_close(Vec3::new(1., 2., 3.), local.direction());
+    }
+
+    #[test]
+    fn frame_is_orthonormal() {
+        for axis in [
+            Vec3::new(1., 0., 0.),
+            Vec3::new(-0.3, 0.2, 0.9),
+            Vec3::new(0., -1., 0.),
+        ]
+        .iter()
+        {
+            let basis = Frame::new(Point3::default(), *axis).basis;
+
+            assert_vec_close(axis.unit_vector(), basis.w);
+            assert_close(0., basis.u.dot(basis.w));
+            assert_close(1., basis.u.len());
+            assert_vec_close(basis.v, basis.u.cross(basis.w));
+        }
+    }
+
+    #[test]
+    fn solve_quadratic_roots() {
+        assert_roots((1., 2.), solve_quadratic(1., -3., 2.));
+        assert_roots((1., 2.), solve_quadratic(-1., 3., -2.));
+        assert_eq!(None, solve_quadratic(1., 0., 1.));
+
+        // Without cancellation, the small root keeps its precision
+        let (t0, t1) = solve_quadratic(1., -1e4, 1.).unwrap();
+        assert_close(1., t0 * 1e4);
+        assert_close(1., t1 / 1e4);
+    }
+
+    #[test]
+    fn solve_quadratic_double_root() {
+        assert_roots((2., 2.), solve_quadratic(1., -4., 4.));
+        assert_roots((0., 0.), solve_quadratic(3., 0., 0.));
+    }
+
+    #[test]
+    fn solve_quadratic_linear() {
+        assert_roots((2., 2.), solve_quadratic(0., 2., -4.));
+        assert_eq!(None, solve_quadratic(0., 0., 1.));
+    }
+}
@@ -0,0 +1,402 @@
+// Terrain from a grid of heights. Rays are walked across the grid a cell at a time, skipping cells they pass over or
+// under, and the cells they might hit are intersected as bilinear patches between their four corners.
+// The work is done in grid space, where cells are one unit across and heights run from 0 to 1.
+
+use super::{frame::solve_quadratic, *};
+use core_img::Rgba8Image;
+
+pub struct Heightfield {
+    /// Vertices along X
+    width: usize,
+    /// Vertices along Z
+    depth: usize,
+    /// Height of each vertex, row by row, in `0.0..1.0`
+    heights: Vec<Num>,
+    /// World space normal of each vertex, averaged from its neighbors so the surface shades smoothly
+    normals: Vec<Vec3>,
+    /// Lowest and highest height in each cell
+    cell_bounds: Vec<(Num, Num)>,
+    /// The lowest corner of the terrain
+    corner: Point3,
+    /// Width, maximum height and depth
+    size: Vec3,
+    material: Material,
+}
+
+impl Heightfield {
+    /// Creates a terrain from a grayscale image, with one vertex per pixel. White is the full height of `size`.
+    /// Image rows run along Z.
+    pub fn from_image(image: &Rgba8Image, corner: Point3, size: Vec3, material: Material) -> Self {
+        let width = image.width() as usize;
+        let depth = image.height() as usize;
+
+        let heights = image
+            .bytes()
+            .chunks(4)
+            .map(|rgba| (rgba[0] as Num + rgba[1] as Num + rgba[2] as Num) / (3. * 255.))
+            .collect();
+
+        Self::new(width, depth, heights, corner, size, material)
+    }
+
+    /// Creates a terrain from heights in `0.0..1.0`, given row by row. There must be `width * depth` of them, and
+    /// every extent of `size` has to be positive.
+    pub fn new(
+        width: usize,
+        depth: usize,
+        heights: Vec<Num>,
+        corner: Point3,
+        size: Vec3,
+        material: Material,
+    ) -> Self {
+        assert_eq!(
+            width * depth,
+            heights.len(),
+            "a {}x{} heightfield needs a height for each vertex",
+            width,
+            depth
+        );
+        assert!(
+            size.x > 0. && size.y > 0. && size.z > 0.,
+            "a heightfield's size has to be positive, not {:?}",
+            size
+        );
+
+        let mut heightfield = Self {
+            width,
+            depth,
+            heights,
+            normals: vec![],
+            cell_bounds: vec![],
+            corner,
+            size,
+            material,
+        };
+
+        heightfield.normals = (0..width * depth)
+            .map(|i| heightfield.vertex_normal(i % width, i / width))
+            .collect();
+
+        let cells_x = width.saturating_sub(1);
+        let cells_z = depth.saturating_sub(1);
+        heightfield.cell_bounds = (0..cells_x * cells_z)
+            .map(|i| {
+                let corners = heightfield.corners(i % cells_x, i / cells_x);
+                let min = corners.iter().cloned().fold(INFINITY, Num::min);
+                let max = corners.iter().cloned().fold(-INFINITY, Num::max);
+                (min, max)
+            })
+            .collect();
+
+        heightfield
+    }
+
+    fn height(&self, x: usize, z: usize) -> Num {
+        self.heights[z * self.width + x]
+    }
+
+    /// Heights of the cell's corners, ordered `(x, z)`, `(x + 1, z)`, `(x, z + 1)`, `(x + 1, z + 1)`.
+    fn corners(&self, x: usize, z: usize) -> [Num; 4] {
+        [
+            self.height(x, z),
+            self.height(x + 1, z),
+            self.height(x, z + 1),
+            self.height(x + 1, z + 1),
+        ]
+    }
+
+    /// The world space size of a cell.
+    fn cell_size(&self) -> (Num, Num) {
+        (
+            self.size.x / (self.width - 1).max(1) as Num,
+            self.size.z / (self.depth - 1).max(1) as Num,
+        )
+    }
+
+    /// The normal at the vertex, from the slope to its neighbors.
+    fn vertex_normal(&self, x: usize, z: usize) -> Vec3 {
+        let (cell_x, cell_z) = self.cell_size();
+
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(self.width - 1));
+        let (z0, z1) = (z.saturating_sub(1), (z + 1).min(self.depth - 1));
+        let slope_x = (self.height(x1, z) - self.height(x0, z)) * self.size.y
+            / ((x1 - x0).max(1) as Num * cell_x);
+        let slope_z = (self.height(x, z1) - self.height(x, z0)) * self.size.y
+            / ((z1 - z0).max(1) as Num * cell_z);
+
+        Vec3::new(-slope_x, 1., -slope_z).unit_vector()
+    }
+
+    /// The ray in grid space. Only the axes are scaled, so distances along it are unchanged.
+    fn grid_ray(&self, ray: &Ray) -> Ray {
+        let scale = Vec3::new(
+            (self.width - 1) as Num / self.size.x,
+            1. / self.size.y,
+            (self.depth - 1) as Num / self.size.z,
+        );
+
+        Ray::new(
+            (ray.origin() - self.corner) * scale,
+            ray.direction() * scale,
+        )
+    }
+
+    /// Where the ray first crosses the cell's patch in `t_enter..t_exit`.
+    fn hit_cell(&self, ray: &Ray, x: usize, z: usize, t_enter: Num, t_exit: Num) -> Option<Num> {
+        // The ray can't hit a cell it stays above or below
+        let (min, max) = self.cell_bounds[z * (self.width - 1) + x];
+        let (y_enter, y_exit) = (ray.at(t_enter).y, ray.at(t_exit).y);
+        if y_enter.min(y_exit) > max || y_enter.max(y_exit) < min {
+            return None;
+        }
+
+        // Along the ray, the bilinear height is quadratic in `t`, so its difference to the ray's height is too
+        let [h00, h10, h01, h11] = self.corners(x, z);
+        let (e, f, g) = (h10 - h00, h01 - h00, h00 - h10 - h01 + h11);
+        let (o, d) = (ray.origin(), ray.direction());
+        let (a, b) = (o.x - x as Num, o.z - z as Num);
+
+        let (t0, t1) = solve_quadratic(
+            -g * d.x * d.z,
+            d.y - (e * d.x + f * d.z + g * (a * d.z + b * d.x)),
+            o.y - (h00 + e * a + f * b + g * a * b),
+        )?;
+
+        [t0, t1]
+            .iter()
+            .cloned()
+            .find(|t| t_enter <= *t && *t <= t_exit)
+    }
+
+    /// Builds the hit record for a hit on the cell, with the normal blended from its corners.
+    fn hit_record(&self, ray: &Ray, grid: &Ray, x: usize, z: usize, t: Num) -> HitRecord {
+        let p = grid.at(t);
+        let fx = (p.x - x as Num).max(0.).min(1.);
+        let fz = (p.z - z as Num).max(0.).min(1.);
+
+        let normal = |x: usize, z: usize| self.normals[z * self.width + x];
+        let outward_normal = ((1. - fx) * (1. - fz) * normal(x, z)
+            + fx * (1. - fz) * normal(x + 1, z)
+            + (1. - fx) * fz * normal(x, z + 1)
+            + fx * fz * normal(x + 1, z + 1))
+        .unit_vector();
+
+        let uv = (p.x / (self.width - 1) as Num, p.z / (self.depth - 1) as Num);
+
+        HitRecord::new(ray.at(t), ray, outward_normal, t, uv, self.material)
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        if self.width < 2 || self.depth < 2 {
+            return None;
+        }
+
+        let grid = self.grid_ray(ray);
+        let (o, d) = (grid.origin(), grid.direction());
+        let cells_x = self.width - 1;
+        let cells_z = self.depth - 1;
+
+        // Clip the ray to the terrain's bounds
+        let mut start = t_min;
+        let mut end = t_max;
+        for (o, d, max) in [
+            (o.x, d.x, cells_x as Num),
+            (o.y, d.y, 1.),
+            (o.z, d.z, cells_z as Num),
+        ]
+        .iter()
+        {
+            let inv = 1. / d;
+            let (t0, t1) = (-o * inv, (max - o) * inv);
+            let (t0, t1) = if inv < 0. { (t1, t0) } else { (t0, t1) };
+
+            start = start.max(t0);
+            end = end.min(t1);
+        }
+        if start > end {
+            return None;
+        }
+
+        // Walk the cells the ray passes over, in order
+        let entry = grid.at(start);
+        let cell = |v: Num, cells: usize| (v.floor().max(0.) as usize).min(cells - 1);
+        let (mut x, mut z) = (cell(entry.x, cells_x), cell(entry.z, cells_z));
+
+        let step = |d: Num| if d > 0. { 1 } else { -1 };
+        let next_edge = |o: Num, d: Num, cell: usize| {
+            if d == 0. {
+                INFINITY
+            } else {
+                let edge = if d > 0. { cell + 1 } else { cell } as Num;
+                (edge - o) / d
+            }
+        };
+        let delta = |d: Num| if d == 0. { INFINITY } else { (1. / d).abs() };
+
+        let (step_x, step_z) = (step(d.x), step(d.z));
+        let (delta_x, delta_z) = (delta(d.x), delta(d.z));
+        let (mut edge_x, mut edge_z) = (next_edge(o.x, d.x, x), next_edge(o.z, d.z, z));
+        let mut t_enter = start;
+
+        loop {
+            let t_exit = edge_x.min(edge_z).min(end);
+            if let Some(t) = self.hit_cell(&grid, x, z, t_enter, t_exit) {
+                return Some(self.hit_record(ray, &grid, x, z, t));
+            }
+
+            if t_exit >= end {
+                return None;
+            }
+
+            // Step into the neighbor across the nearest edge
+            let (next_x, next_z) = if edge_x < edge_z {
+                t_enter = edge_x;
+                edge_x += delta_x;
+                (x as isize + step_x, z as isize)
+            } else {
+                t_enter = edge_z;
+                edge_z += delta_z;
+                (x as isize, z as isize + step_z)
+            };
+
+            if next_x < 0 || next_x >= cells_x as isize || next_z < 0 || next_z >= cells_z as isize
+            {
+                return None;
+            }
+            x = next_x as usize;
+            z = next_z as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rows along X of `0, 0, 0, 0.5, 0`, with cells one unit across and heights doubled. Flat up to `x = 2`, then
+    /// a ridge along Z that peaks at `x = 3`, with a height of 1.
+    fn ridge() -> Heightfield {
+        let row = [0., 0., 0., 0.5, 0.];
+        let heights = row.iter().cycle().take(15).cloned().collect();
+
+        Heightfield::new(
+            5,
+            3,
+            heights,
+            Point3::default(),
+            Vec3::new(4., 2., 2.),
+            test_material(),
+        )
+    }
+
+    fn down_from(x: Num, z: Num) -> Ray {
+        Ray::new(Point3::new(x, 3., z), Vec3::new(0., -1., 0.))
+    }
+
+    #[test]
+    fn heightfield_hit_flat_cell() {
+        let rec = ridge().hit(&down_from(0.5, 1.5), 0.001, INFINITY).unwrap();
+
+        assert_close(3., rec.t);
+        assert!(rec.front_face);
+        assert_close(0., rec.normal.x);
+        assert_close(1., rec.normal.y);
+        assert_close(0.125, rec.u);
+        assert_close(0.75, rec.v);
+    }
+
+    #[test]
+    fn heightfield_hit_sloped_cell() {
+        let rec = ridge().hit(&down_from(2.5, 0.5), 0.001, INFINITY).unwrap();
+        assert_close(2.5, rec.t);
+        assert_close(0.625, rec.u);
+        assert_close(0.25, rec.v);
+
+        // Halfway between the vertex normals at `x = 2`, on the slope, and `x = 3`, on the peak
+        let expected = (Vec3::new(-0.5, 1., 0.).unit_vector() + Vec3::unit_y()).unit_vector();
+        assert_close(expected.x, rec.normal.x);
+        assert_close(expected.y, rec.normal.y);
+        assert_close(0., rec.normal.z);
+    }
+
+    #[test]
+    fn heightfield_hit_across_cells() {
+        // Passes over two flat cells, and across a row of cells, before hitting the slope at `(2.2, 0.2, 1.3)`
+        let ray = Ray::new(Point3::new(0.2, 0.6, 0.5), Vec3::new(1., -0.2, 0.4));
+        let rec = ridge().hit(&ray, 0.001, INFINITY).unwrap();
+
+        assert_close(2., rec.t);
+        assert_close(2.2, rec.point.x);
+        assert_close(0.2, rec.point.y);
+        assert_close(1.3, rec.point.z);
+    }
+
+    #[test]
+    fn heightfield_grazing_ray_misses_ridge() {
+        let ridge = ridge();
+
+        // Clears the peak, and then the far side of it, though it's within the height of the cells there
+        let ray = Ray::new(Point3::new(2., 1.2, 1.), Vec3::new(1., -0.15, 0.));
+        assert!(ridge.hit(&ray, 0.001, INFINITY).is_none());
+        let ray = Ray::new(Point3::new(0.5, 1.01, 0.7), Vec3::new(1., 0., 0.1));
+        assert!(ridge.hit(&ray, 0.001, INFINITY).is_none());
+
+        // A little steeper and it hits the near side
+        let ray = Ray::new(Point3::new(2., 1.2, 1.), Vec3::new(1., -0.25, 0.));
+        let rec = ridge.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(0.96, rec.t);
+        assert!(rec.front_face);
+    }
+
+    #[test]
+    fn heightfield_hit_bilinear_patch() {
+        // A single cell with one raised corner, where the height is `x * z`
+        let heightfield = Heightfield::new(
+            2,
+            2,
+            vec![0., 0., 0., 1.],
+            Point3::default(),
+            Vec3::new(1., 1., 1.),
+            test_material(),
+        );
+
+        let rec = heightfield
+            .hit(&down_from(0.8, 0.6), 0.001, INFINITY)
+            .unwrap();
+        assert_close(3. - 0.48, rec.t);
+
+        // Along a ray crossing both axes, the height is quadratic
+        let ray = Ray::new(Point3::new(0., 1., 0.5), Vec3::new(1., -1., 0.25));
+        let rec = heightfield.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close((-1.5 + (3.25 as Num).sqrt()) / 0.5, rec.t);
+        assert_close(rec.point.x * rec.point.z, rec.point.y);
+    }
+
+    #[test]
+    #[should_panic(expected = "a heightfield's size has to be positive")]
+    fn heightfield_size_is_positive() {
+        Heightfield::new(
+            2,
+            2,
+            vec![0.; 4],
+            Point3::default(),
+            Vec3::new(1., 0., 1.),
+            test_material(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "a 3x2 heightfield needs a height for each vertex")]
+    fn heightfield_needs_every_height() {
+        Heightfield::new(
+            3,
+            2,
+            vec![0.; 5],
+            Point3::default(),
+            Vec3::new(1., 1., 1.),
+            test_material(),
+        );
+    }
+}
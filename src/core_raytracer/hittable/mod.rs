@@ -1,24 +1,52 @@
 use super::{
     material::Material,
-    ray::Ray,
+    num::{Num, INFINITY, PI},
+    ray::{offset_origin, Ray},
     vec3::{Point3, Vec3},
 };
 
+pub mod capsule;
+pub mod cone;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+mod frame;
+pub mod heightfield;
+pub mod paraboloid;
+pub mod sdf;
 pub mod sphere;
+pub mod sphere_pack;
+pub mod torus;
 mod world;
 
+pub use frame::Frame;
 pub use world::*;
 
+/// The most surfaces found along a ray when listing crossings, in case a shape isn't closed.
+const MAX_CROSSINGS: usize = 64;
+/// How far past a crossing the search for the next one starts, relative to its distance.
+const CROSSING_EPSILON: Num = 1e-4;
+
 pub struct HitRecord {
     pub point: Point3,
     pub normal: Vec3,
-    pub t: f32,
+    pub t: Num,
+    /// Surface coordinates, each in `0.0..1.0`
+    pub u: Num,
+    pub v: Num,
     pub front_face: bool,
     pub material: Material,
 }
 
 impl HitRecord {
-    pub fn new(point: Point3, ray: &Ray, outward_normal: Vec3, t: f32, material: Material) -> Self {
+    pub fn new(
+        point: Point3,
+        ray: &Ray,
+        outward_normal: Vec3,
+        t: Num,
+        uv: (Num, Num),
+        material: Material,
+    ) -> Self {
         let front_face = ray.direction().dot(outward_normal) < 0.;
         let normal = if front_face {
             outward_normal
@@ -31,11 +59,71 @@ impl HitRecord {
             normal,
             front_face,
             t,
+            u: uv.0,
+            v: uv.1,
             material,
         }
     }
+
+    /// Creates a ray leaving the surface in the given direction, offset to avoid hitting the surface again.
+    pub fn spawn_ray(&self, direction: Vec3) -> Ray {
+        let normal = if direction.dot(self.normal) < 0. {
+            -self.normal
+        } else {
+            self.normal
+        };
+
+        Ray::new(offset_origin(self.point, normal), direction)
+    }
 }
 
 pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord>;
+
+    /// Every surface the ray crosses in `t_min..t_max`, in order. Only meaningful for closed shapes, where
+    /// `front_face` tells whether the ray is entering or leaving the shape at each one.
+    fn crossings(&self, ray: &Ray, t_min: Num, t_max: Num) -> Vec<HitRecord> {
+        let mut crossings = vec![];
+        let mut t = t_min;
+
+        while crossings.len() < MAX_CROSSINGS {
+            let rec = match self.hit(ray, t, t_max) {
+                Some(rec) => rec,
+                None => break,
+            };
+
+            t = rec.t + CROSSING_EPSILON * rec.t.abs().max(1.);
+            crossings.push(rec);
+        }
+
+        crossings
+    }
+}
+
+/// A plain material for shapes under test.
+#[cfg(test)]
+fn test_material() -> Material {
+    Material::Lambertian {
+        albedo: Vec3::new(0.5, 0.5, 0.5),
+    }
+}
+
+#[cfg(test)]
+fn assert_vec_close(expected: Vec3, actual: Vec3) {
+    assert!(
+        (expected - actual).len() < 1e-4,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+#[cfg(test)]
+fn assert_close(expected: Num, actual: Num) {
+    assert!(
+        (expected - actual).abs() < 1e-4,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
 }
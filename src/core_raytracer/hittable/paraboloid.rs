@@ -0,0 +1,119 @@
+use super::{
+    frame::{around_axis, closest, disk, solve_quadratic, Frame, LocalHit},
+    *,
+};
+
+/// A bowl with its lowest point at the base, opening along the frame's axis.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Paraboloid {
+    frame: Frame,
+    /// Radius of the rim
+    radius: Num,
+    height: Num,
+    /// Whether the rim is closed
+    capped: bool,
+    material: Material,
+}
+
+impl Paraboloid {
+    pub fn new(frame: Frame, radius: Num, height: Num, capped: bool, material: Material) -> Self {
+        Self {
+            frame,
+            radius,
+            height,
+            capped,
+            material,
+        }
+    }
+
+    fn side(&self, ray: &Ray, t: Num) -> Option<LocalHit> {
+        let p = ray.at(t);
+        if p.y < 0. || p.y > self.height {
+            return None;
+        }
+
+        // The gradient of `s (x² + z²) - y`
+        let s = self.height / (self.radius * self.radius);
+        Some(LocalHit {
+            t,
+            normal: Vec3::new(2. * s * p.x, -1., 2. * s * p.z),
+            uv: (around_axis(p), p.y / self.height),
+        })
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        let local = self.frame.local_ray(ray);
+        let (o, d) = (local.origin(), local.direction());
+
+        // The surface is `y = s (x² + z²)`, reaching the rim at the full height
+        let s = self.height / (self.radius * self.radius);
+        let a = s * (d.x * d.x + d.z * d.z);
+        let b = 2. * s * (o.x * d.x + o.z * d.z) - d.y;
+        let c = s * (o.x * o.x + o.z * o.z) - o.y;
+        let (t0, t1) = solve_quadratic(a, b, c).unwrap_or((-INFINITY, -INFINITY));
+
+        let rim = if self.capped {
+            disk(&local, self.height, self.radius, true)
+        } else {
+            None
+        };
+
+        let hit = closest(
+            [self.side(&local, t0), self.side(&local, t1), rim],
+            t_min,
+            t_max,
+        )?;
+
+        Some(self.frame.hit_record(ray, hit, self.material))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The bowl `y = x² + z²`, with its rim at a height of 1.
+    fn bowl(capped: bool) -> Paraboloid {
+        let frame = Frame::new(Point3::default(), Vec3::unit_y());
+        Paraboloid::new(frame, 1., 1., capped, test_material())
+    }
+
+    #[test]
+    fn paraboloid_hit_side() {
+        let ray = Ray::new(Point3::new(0., 0.25, 3.), Vec3::new(0., 0., -1.));
+        let rec = bowl(false).hit(&ray, 0.001, INFINITY).unwrap();
+
+        assert_close(2.5, rec.t);
+        assert!(rec.front_face);
+        assert_vec_close(Vec3::new(0., -1., 1.).unit_vector(), rec.normal);
+        assert_close(0.25, rec.u);
+        assert_close(0.25, rec.v);
+    }
+
+    #[test]
+    fn paraboloid_hit_rim() {
+        let ray = Ray::new(Point3::new(0.5, 3., 0.), Vec3::new(0., -1., 0.));
+        let rec = bowl(true).hit(&ray, 0.001, INFINITY).unwrap();
+
+        assert_close(2., rec.t);
+        assert!(rec.front_face);
+        assert_vec_close(Vec3::new(0., 1., 0.), rec.normal);
+        assert_close(0.75, rec.u);
+        assert_close(0.5, rec.v);
+    }
+
+    #[test]
+    fn paraboloid_open_seen_from_inside() {
+        // In through the open rim, and onto the inside of the bowl, where the normal faces back up
+        let ray = Ray::new(Point3::new(0.5, 2., 0.), Vec3::new(0., -1., 0.));
+        let rec = bowl(false).hit(&ray, 0.001, INFINITY).unwrap();
+
+        assert_close(1.75, rec.t);
+        assert!(!rec.front_face);
+        assert_vec_close(-Vec3::new(1., -1., 0.).unit_vector(), rec.normal);
+        assert_close(0.5, rec.u);
+        assert_close(0.25, rec.v);
+    }
+}
@@ -0,0 +1,481 @@
+// Shapes described by signed distance functions, rendered by sphere tracing. The distance to the surface is always
+// a safe step along the ray, so the ray is marched until it's close enough to count as a hit.
+// The primitives and smooth operators follow Inigo Quilez's articles on distance functions.
+
+use super::{sphere::sphere_uv, *};
+
+/// The most steps a ray is marched before it's treated as a miss.
+const MAX_STEPS: u32 = 256;
+/// Points closer to the surface than this are hits.
+const SURFACE_DISTANCE: Num = 1e-4;
+/// Offset used to estimate the gradient for normals.
+const NORMAL_EPSILON: Num = 1e-4;
+/// Rays stop being marched this far away, for shapes without bounds.
+const MAX_DISTANCE: Num = 1e3;
+
+/// An expression tree that gives the signed distance to a surface, negative inside.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sdf {
+    Sphere {
+        radius: Num,
+    },
+    Box {
+        half_extents: Vec3,
+    },
+    /// Lies in the XZ plane
+    Torus {
+        major_radius: Num,
+        minor_radius: Num,
+    },
+    Capsule {
+        a: Point3,
+        b: Point3,
+        radius: Num,
+    },
+    /// Everything below the plane is inside
+    Plane {
+        normal: Vec3,
+        offset: Num,
+    },
+    Translate {
+        offset: Vec3,
+        sdf: Box<Sdf>,
+    },
+    /// Blends the shapes together over `k`. `0` is a sharp union.
+    SmoothUnion {
+        a: Box<Sdf>,
+        b: Box<Sdf>,
+        k: Num,
+    },
+    /// Cuts `b` out of `a`, blending over `k`
+    SmoothSubtract {
+        a: Box<Sdf>,
+        b: Box<Sdf>,
+        k: Num,
+    },
+    SmoothIntersect {
+        a: Box<Sdf>,
+        b: Box<Sdf>,
+        k: Num,
+    },
+    /// Repeats the shape forever, every `period` along each axis. Axes with a period of `0` aren't repeated.
+    /// The shape should fit within one period.
+    Repeat {
+        period: Vec3,
+        sdf: Box<Sdf>,
+    },
+    /// Twists the shape around the Y axis, by `rate` radians per unit of height
+    Twist {
+        rate: Num,
+        sdf: Box<Sdf>,
+    },
+}
+
+impl Sdf {
+    pub fn sphere(radius: Num) -> Self {
+        Sdf::Sphere { radius }
+    }
+
+    pub fn cuboid(half_extents: Vec3) -> Self {
+        Sdf::Box { half_extents }
+    }
+
+    pub fn torus(major_radius: Num, minor_radius: Num) -> Self {
+        Sdf::Torus {
+            major_radius,
+            minor_radius,
+        }
+    }
+
+    pub fn capsule(a: Point3, b: Point3, radius: Num) -> Self {
+        Sdf::Capsule { a, b, radius }
+    }
+
+    pub fn plane(normal: Vec3, offset: Num) -> Self {
+        Sdf::Plane {
+            normal: normal.unit_vector(),
+            offset,
+        }
+    }
+
+    pub fn translate(self, offset: Vec3) -> Self {
+        Sdf::Translate {
+            offset,
+            sdf: Box::new(self),
+        }
+    }
+
+    pub fn smooth_union(self, other: Sdf, k: Num) -> Self {
+        Sdf::SmoothUnion {
+            a: Box::new(self),
+            b: Box::new(other),
+            k,
+        }
+    }
+
+    pub fn smooth_subtract(self, other: Sdf, k: Num) -> Self {
+        Sdf::SmoothSubtract {
+            a: Box::new(self),
+            b: Box::new(other),
+            k,
+        }
+    }
+
+    pub fn smooth_intersect(self, other: Sdf, k: Num) -> Self {
+        Sdf::SmoothIntersect {
+            a: Box::new(self),
+            b: Box::new(other),
+            k,
+        }
+    }
+
+    pub fn repeat(self, period: Vec3) -> Self {
+        Sdf::Repeat {
+            period,
+            sdf: Box::new(self),
+        }
+    }
+
+    pub fn twist(self, rate: Num) -> Self {
+        Sdf::Twist {
+            rate,
+            sdf: Box::new(self),
+        }
+    }
+
+    /// The signed distance from the point to the surface. May underestimate, but never overestimates by more than
+    /// `max_stretch`.
+    pub fn distance(&self, p: Point3) -> Num {
+        match self {
+            Sdf::Sphere { radius } => p.len() - radius,
+            Sdf::Box { half_extents } => {
+                let q = Vec3::new(
+                    p.x.abs() - half_extents.x,
+                    p.y.abs() - half_extents.y,
+                    p.z.abs() - half_extents.z,
+                );
+                let outside = Vec3::new(q.x.max(0.), q.y.max(0.), q.z.max(0.)).len();
+                let inside = q.x.max(q.y).max(q.z).min(0.);
+
+                outside + inside
+            }
+            Sdf::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let ring = (p.x * p.x + p.z * p.z).sqrt() - major_radius;
+                (ring * ring + p.y * p.y).sqrt() - minor_radius
+            }
+            Sdf::Capsule { a, b, radius } => {
+                let pa = p - *a;
+                let ba = *b - *a;
+                let h = (pa.dot(ba) / ba.len_sqrd()).max(0.).min(1.);
+
+                (pa - h * ba).len() - radius
+            }
+            Sdf::Plane { normal, offset } => p.dot(*normal) - offset,
+            Sdf::Translate { offset, sdf } => sdf.distance(p - *offset),
+            Sdf::SmoothUnion { a, b, k } => smooth_min(a.distance(p), b.distance(p), *k),
+            Sdf::SmoothSubtract { a, b, k } => -smooth_min(-a.distance(p), b.distance(p), *k),
+            Sdf::SmoothIntersect { a, b, k } => -smooth_min(-a.distance(p), -b.distance(p), *k),
+            Sdf::Repeat { period, sdf } => {
+                let wrap = |v: Num, period: Num| {
+                    if period > 0. {
+                        v - period * (v / period).round()
+                    } else {
+                        v
+                    }
+                };
+
+                sdf.distance(Vec3::new(
+                    wrap(p.x, period.x),
+                    wrap(p.y, period.y),
+                    wrap(p.z, period.z),
+                ))
+            }
+            Sdf::Twist { rate, sdf } => {
+                let (sin, cos) = (-rate * p.y).sin_cos();
+                sdf.distance(Vec3::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z))
+            }
+        }
+    }
+
+    /// How much the operators stretch distances within `extent` of the origin. Twisting moves points further the
+    /// further they are from the axis, so the distance it gives can be too large and has to be scaled down.
+    pub fn max_stretch(&self, extent: Num) -> Num {
+        match self {
+            Sdf::Sphere { .. }
+            | Sdf::Box { .. }
+            | Sdf::Torus { .. }
+            | Sdf::Capsule { .. }
+            | Sdf::Plane { .. } => 1.,
+            Sdf::Translate { offset, sdf } => sdf.max_stretch(extent + offset.len()),
+            Sdf::SmoothUnion { a, b, .. }
+            | Sdf::SmoothSubtract { a, b, .. }
+            | Sdf::SmoothIntersect { a, b, .. } => a.max_stretch(extent).max(b.max_stretch(extent)),
+            Sdf::Repeat { sdf, .. } => sdf.max_stretch(extent),
+            Sdf::Twist { rate, sdf } => {
+                let twist = rate * extent;
+                (1. + twist * twist).sqrt() * sdf.max_stretch(extent)
+            }
+        }
+    }
+
+    /// The outward normal at the point, from the gradient of the distance.
+    fn normal(&self, p: Point3) -> Vec3 {
+        // Samples the corners of a tetrahedron, which takes one less sample than central differences
+        let e = NORMAL_EPSILON;
+        let corners = [
+            Vec3::new(e, -e, -e),
+            Vec3::new(-e, -e, e),
+            Vec3::new(-e, e, -e),
+            Vec3::new(e, e, e),
+        ];
+
+        let mut gradient = Vec3::default();
+        for corner in corners.iter() {
+            gradient += *corner * self.distance(p + *corner);
+        }
+
+        gradient.unit_vector()
+    }
+}
+
+/// Polynomial smooth minimum, blending `a` and `b` where they're within `k` of each other.
+fn smooth_min(a: Num, b: Num, k: Num) -> Num {
+    if k <= 0. {
+        return a.min(b);
+    }
+
+    let h = (0.5 + 0.5 * (b - a) / k).max(0.).min(1.);
+    b + (a - b) * h - k * h * (1. - h)
+}
+
+/// A hittable shape described by a signed distance function.
+pub struct SdfShape {
+    sdf: Sdf,
+    /// A sphere the surface fits within, as its center and radius. Rays that miss it aren't marched.
+    bounds: Option<(Point3, Num)>,
+    /// Scales each step, so shapes that stretch distances aren't overshot
+    step_scale: Num,
+    material: Material,
+}
+
+impl SdfShape {
+    /// Creates the shape. `bounds` is a sphere the surface fits within, which should be given unless the shape is
+    /// infinite, as it's much faster to skip rays that miss it.
+    pub fn new(sdf: Sdf, bounds: Option<(Point3, Num)>, material: Material) -> Self {
+        // Without bounds there's no telling how far out a twist goes, so assume the unit sphere
+        let extent = bounds.map_or(1., |(center, radius)| center.len() + radius);
+        let step_scale = 1. / sdf.max_stretch(extent);
+
+        Self {
+            sdf,
+            bounds,
+            step_scale,
+            material,
+        }
+    }
+
+    /// The part of the ray within the bounds, if any of it is.
+    fn clip(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<(Num, Num)> {
+        let (center, radius) = match self.bounds {
+            Some(bounds) => bounds,
+            None => return Some((t_min, t_max.min(MAX_DISTANCE / ray.direction().len()))),
+        };
+
+        let oc = ray.origin() - center;
+        let a = ray.direction().len_sqrd();
+        let half_b = oc.dot(ray.direction());
+        let c = oc.len_sqrd() - radius * radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0. {
+            return None;
+        }
+
+        let sqrtd = discriminant.sqrt();
+        let start = ((-half_b - sqrtd) / a).max(t_min);
+        let end = ((-half_b + sqrtd) / a).min(t_max);
+
+        if start < end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+}
+
+impl Hittable for SdfShape {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        let (start, end) = self.clip(ray, t_min, t_max)?;
+
+        // Distances are measured in space, but steps are along the ray
+        let speed = ray.direction().len();
+        if speed <= 0. {
+            return None;
+        }
+
+        let mut t = start;
+        let d = self.sdf.distance(ray.at(t));
+
+        // Rays leaving the surface, such as ones spawned from it, start within the hit distance of it. They're marched
+        // away from it before they can hit anything, on whichever side they're heading into. Rays that start further
+        // along, where they enter the bounds, come from outside and hit the surface there if it touches the bounds.
+        let mut leaving = start == t_min && d.abs() < SURFACE_DISTANCE;
+        let side = if leaving {
+            let gradient = self.sdf.normal(ray.at(t));
+            if gradient.dot(ray.direction()) >= 0. {
+                1.
+            } else {
+                -1.
+            }
+        } else {
+            d.signum()
+        };
+
+        for _ in 0..MAX_STEPS {
+            let p = ray.at(t);
+            let d = side * self.sdf.distance(p);
+
+            if d < SURFACE_DISTANCE && !leaving {
+                let outward_normal = self.sdf.normal(p);
+                let uv = sphere_uv(outward_normal);
+
+                return Some(HitRecord::new(p, ray, outward_normal, t, uv, self.material));
+            }
+
+            if d >= SURFACE_DISTANCE {
+                leaving = false;
+            }
+
+            t += d.abs().max(SURFACE_DISTANCE) * self.step_scale / speed;
+            if t > end {
+                return None;
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(sdf: Sdf, bounds: Option<(Point3, Num)>) -> SdfShape {
+        SdfShape::new(sdf, bounds, test_material())
+    }
+
+    #[test]
+    fn smooth_min_blends_within_k() {
+        assert_close(1., smooth_min(1., 2., 0.));
+        assert_close(1., smooth_min(1., 2., -1.));
+
+        // Further apart than `k` is a plain minimum
+        assert_close(1., smooth_min(1., 2., 0.5));
+        assert_close(1., smooth_min(2., 1., 0.5));
+
+        // Equal values are pulled down the most, by a quarter of `k`
+        assert_close(0.875, smooth_min(1., 1., 0.5));
+        assert!(smooth_min(1., 1.2, 0.5) < 1.);
+    }
+
+    #[test]
+    fn sdf_primitive_distances() {
+        let sphere = Sdf::sphere(1.);
+        assert_close(1., sphere.distance(Point3::new(0., 2., 0.)));
+        assert_close(-1., sphere.distance(Point3::default()));
+
+        let cuboid = Sdf::cuboid(Vec3::new(1., 2., 3.));
+        assert_close(1., cuboid.distance(Point3::new(2., 0., 0.)));
+        assert_close(-1., cuboid.distance(Point3::new(0., 1., 0.)));
+        assert_close((2. as Num).sqrt(), cuboid.distance(Point3::new(2., 3., 0.)));
+
+        let torus = Sdf::torus(2., 0.5);
+        assert_close(-0.5, torus.distance(Point3::new(0., 0., 2.)));
+        assert_close(1.5, torus.distance(Point3::default()));
+        assert_close(0.5, torus.distance(Point3::new(2., 1., 0.)));
+
+        let capsule = Sdf::capsule(Point3::default(), Point3::new(0., 2., 0.), 0.5);
+        assert_close(0.5, capsule.distance(Point3::new(1., 1., 0.)));
+        assert_close(0.5, capsule.distance(Point3::new(0., 3., 0.)));
+        assert_close(-0.5, capsule.distance(Point3::new(0., 1., 0.)));
+
+        // The normal is normalized
+        let plane = Sdf::plane(Vec3::new(0., 2., 0.), 1.);
+        assert_close(2., plane.distance(Point3::new(5., 3., 5.)));
+        assert_close(-1., plane.distance(Point3::default()));
+    }
+
+    #[test]
+    fn sdf_operator_distances() {
+        let sphere = || Sdf::sphere(1.);
+        let moved = || sphere().translate(Vec3::new(1.5, 0., 0.));
+        assert_close(-1., moved().distance(Point3::new(1.5, 0., 0.)));
+
+        // With `k` of 0 the smooth operators are sharp
+        let p = Point3::new(0.75, 0., 0.);
+        let (a, b) = (sphere().distance(p), moved().distance(p));
+        assert_close(a.min(b), sphere().smooth_union(moved(), 0.).distance(p));
+        assert_close(a.max(-b), sphere().smooth_subtract(moved(), 0.).distance(p));
+        assert_close(a.max(b), sphere().smooth_intersect(moved(), 0.).distance(p));
+
+        // Blending grows the union and shrinks the subtraction and intersection
+        assert!(sphere().smooth_union(moved(), 1.).distance(p) < a.min(b));
+        assert!(sphere().smooth_subtract(moved(), 1.).distance(p) > a.max(-b));
+        assert!(sphere().smooth_intersect(moved(), 1.).distance(p) > a.max(b));
+
+        // Axes with a period of 0 aren't repeated
+        let repeated = Sdf::sphere(0.5).repeat(Vec3::new(2., 0., 0.));
+        assert_close(-0.5, repeated.distance(Point3::new(4., 0., 0.)));
+        assert_close(0.5, repeated.distance(Point3::new(1., 0., 0.)));
+        assert_close(3.5, repeated.distance(Point3::new(0., 4., 0.)));
+
+        // A quarter turn per unit of height turns the X axis onto the Z axis
+        let twisted = Sdf::cuboid(Vec3::new(1., 2., 0.1)).twist(PI / 2.);
+        assert_close(-0.1, twisted.distance(Point3::new(0., 0., 0.)));
+        assert_close(-0.1, twisted.distance(Point3::new(0., 1., 0.9)));
+        assert_close(0.4, twisted.distance(Point3::new(0.5, 1., 0.)));
+    }
+
+    #[test]
+    fn sdf_shape_hit_from_outside() {
+        let sphere = shape(Sdf::sphere(1.), Some((Point3::default(), 1.)));
+        let ray = Ray::new(Point3::new(0., 0., 3.), Vec3::new(0., 0., -1.));
+
+        let rec = sphere.hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(2., rec.t);
+        assert!(rec.front_face);
+        assert_close(1., rec.normal.z);
+    }
+
+    #[test]
+    fn sdf_shape_rays_leaving_the_surface() {
+        let sphere = shape(Sdf::sphere(1.), Some((Point3::default(), 1.)));
+
+        // Spawned outwards from the surface; there's nothing else to hit
+        let ray = Ray::new(Point3::new(0., 0., 1.), Vec3::new(0., 0., 1.));
+        assert!(sphere.hit(&ray, 0., INFINITY).is_none());
+
+        // Spawned inwards, as by refraction; hits the far side from inside
+        let ray = Ray::new(Point3::new(0., 0., 1.), Vec3::new(0., 0., -1.));
+        let rec = sphere.hit(&ray, 0., INFINITY).unwrap();
+        assert_close(2., rec.t);
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn sdf_shape_unbounded_max_distance() {
+        // Everything below y = -500 is inside
+        let ground = shape(Sdf::plane(Vec3::unit_y(), -500.), None);
+
+        // The distance limit is in space, not in units of the ray's direction
+        let ray = Ray::new(Point3::default(), Vec3::new(0., -0.1, 0.));
+        let rec = ground.hit(&ray, 0.001, INFINITY).unwrap();
+        assert!((rec.t - 5000.).abs() < 0.1);
+
+        let ray = Ray::new(Point3::default(), Vec3::new(0., 10., 0.));
+        let sky = shape(Sdf::plane(-Vec3::unit_y(), -1500.), None);
+        assert!(sky.hit(&ray, 0.001, INFINITY).is_none());
+    }
+}
@@ -1,24 +1,37 @@
 use super::*;
 use crate::core_raytracer::vec3::Point3;
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Sphere {
     center: Point3,
-    radius: f32,
+    radius: Num,
     material: Material,
 }
 
 impl Sphere {
-    pub fn new(center: Point3, radius: f32, material: Material) -> Self {
+    pub fn new(center: Point3, radius: Num, material: Material) -> Self {
         Self {
             center,
             radius,
             material,
         }
     }
+
+    pub fn center(&self) -> Point3 {
+        self.center
+    }
+
+    pub fn radius(&self) -> Num {
+        self.radius
+    }
+
+    pub fn material(&self) -> Material {
+        self.material
+    }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
         let oc = ray.origin() - self.center;
         let a = ray.direction().len_sqrd();
         let half_b = oc.dot(ray.direction());
@@ -34,19 +47,44 @@ impl Hittable for Sphere {
         let mut root = (-half_b - sqrtd) / a;
         if root < t_min || t_max < root {
             root = (-half_b + sqrtd) / a;
-            if root < t_max || t_max < root {
+            if root < t_min || t_max < root {
                 return None;
             }
         }
         let t = root;
-        let point = ray.at(t);
-        let outward_normal: Vec3 = (point - self.center) / self.radius;
+        let (point, outward_normal) = surface_point(self.center, self.radius, ray, t);
+        let uv = sphere_uv(outward_normal);
 
-        Some(HitRecord::new(point, ray, outward_normal, t, self.material))
+        Some(HitRecord::new(
+            point,
+            ray,
+            outward_normal,
+            t,
+            uv,
+            self.material,
+        ))
     }
 }
 
-fn hit_sphere(center: &Point3, radius: f32, ray: &Ray) -> f32 {
+/// Returns the hit point and outward normal for the ray at `t`.
+/// The point is projected back onto the surface, removing the error from evaluating the ray.
+pub(super) fn surface_point(center: Point3, radius: Num, ray: &Ray, t: Num) -> (Point3, Vec3) {
+    let outward_normal = (ray.at(t) - center).unit_vector();
+
+    (center + radius * outward_normal, outward_normal)
+}
+
+/// Returns the UV coordinates for a point on the unit sphere.
+/// `u` wraps around the Y axis starting at -X, `v` goes from -Y to +Y.
+pub(super) fn sphere_uv(p: Vec3) -> (Num, Num) {
+    let pi = PI;
+    let theta = (-p.y).max(-1.).min(1.).acos();
+    let phi = (-p.z).atan2(p.x) + pi;
+
+    (phi / (2. * pi), theta / pi)
+}
+
+fn hit_sphere(center: &Point3, radius: Num, ray: &Ray) -> Num {
     let oc = ray.origin() - *center;
     let a = ray.direction().len_sqrd();
     let half_b = oc.dot(ray.direction());
@@ -0,0 +1,293 @@
+use super::{sphere::Sphere, *};
+use crate::core_raytracer::simd::{Wide, LANES};
+
+/// A ray splatted across all lanes.
+struct RayLanes {
+    ox: Wide,
+    oy: Wide,
+    oz: Wide,
+    dx: Wide,
+    dy: Wide,
+    dz: Wide,
+    /// Squared length of the direction
+    a: Wide,
+}
+
+impl RayLanes {
+    fn new(ray: &Ray) -> Self {
+        let origin = ray.origin();
+        let dir = ray.direction();
+
+        Self {
+            ox: Wide::splat(origin.x),
+            oy: Wide::splat(origin.y),
+            oz: Wide::splat(origin.z),
+            dx: Wide::splat(dir.x),
+            dy: Wide::splat(dir.y),
+            dz: Wide::splat(dir.z),
+            a: Wide::splat(dir.len_sqrd()),
+        }
+    }
+}
+
+/// Spheres stored as structure-of-arrays, so rays can be tested against `LANES` of them at a time.
+pub struct SpherePack {
+    center_x: Vec<Num>,
+    center_y: Vec<Num>,
+    center_z: Vec<Num>,
+    radius: Vec<Num>,
+    materials: Vec<Material>,
+}
+
+impl SpherePack {
+    pub fn new() -> Self {
+        Self {
+            center_x: vec![],
+            center_y: vec![],
+            center_z: vec![],
+            radius: vec![],
+            materials: vec![],
+        }
+    }
+
+    pub fn push(&mut self, sphere: Sphere) {
+        let center = sphere.center();
+
+        // Replace padding if it exists, otherwise extend by a full set of lanes
+        let len = self.materials.len();
+        if len % LANES == 0 {
+            for lanes in [
+                &mut self.center_x,
+                &mut self.center_y,
+                &mut self.center_z,
+                &mut self.radius,
+            ]
+            .iter_mut()
+            {
+                lanes.extend_from_slice(&[0.; LANES]);
+            }
+        }
+
+        self.center_x[len] = center.x;
+        self.center_y[len] = center.y;
+        self.center_z[len] = center.z;
+        self.radius[len] = sphere.radius();
+        self.materials.push(sphere.material());
+    }
+
+    /// The number of spheres
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn get(&self, i: usize) -> Sphere {
+        let center = Point3::new(self.center_x[i], self.center_y[i], self.center_z[i]);
+
+        Sphere::new(center, self.radius[i], self.materials[i])
+    }
+
+    pub fn set(&mut self, i: usize, sphere: Sphere) {
+        let center = sphere.center();
+
+        self.center_x[i] = center.x;
+        self.center_y[i] = center.y;
+        self.center_z[i] = center.z;
+        self.radius[i] = sphere.radius();
+        self.materials[i] = sphere.material();
+    }
+
+    /// Removes the sphere at `i`, moving the last sphere into its place.
+    pub fn swap_remove(&mut self, i: usize) {
+        let last = self.len() - 1;
+        self.materials.swap_remove(i);
+
+        for lanes in [
+            &mut self.center_x,
+            &mut self.center_y,
+            &mut self.center_z,
+            &mut self.radius,
+        ]
+        .iter_mut()
+        {
+            lanes[i] = lanes[last];
+            lanes[last] = 0.;
+
+            // Drop the last set of lanes once it's only padding
+            if last % LANES == 0 {
+                lanes.truncate(last);
+            }
+        }
+    }
+
+    /// Returns the index and distance of the closest sphere hit in `t_min..t_max`.
+    /// Adds the number of sets of lanes tested to `tests`.
+    fn closest(&self, ray: &Ray, t_min: Num, t_max: Num, tests: &mut u32) -> Option<(usize, Num)> {
+        let lanes = RayLanes::new(ray);
+
+        let mut closest = None;
+        let mut closest_t = t_max;
+
+        for start in (0..self.len()).step_by(LANES) {
+            *tests += 1;
+            let t = self.hit_lanes(start, &lanes, t_min, closest_t);
+            let live_lanes = (self.len() - start).min(LANES);
+            let hits = self.hit_mask(t, start);
+            if hits == 0 {
+                continue;
+            }
+
+            let t = t.to_array();
+            for lane in 0..live_lanes {
+                // Hits are already within `t_max`, which they can be equal to
+                if hits & (1 << lane) != 0 && (closest.is_none() || t[lane] < closest_t) {
+                    closest_t = t[lane];
+                    closest = Some(start + lane);
+                }
+            }
+        }
+
+        closest.map(|i| (i, closest_t))
+    }
+
+    /// Packs the lanes that hit into a bitmask. Padding lanes are never set.
+    fn hit_mask(&self, t: Wide, start: usize) -> u32 {
+        let live_lanes = (self.len() - start).min(LANES);
+        t.lt(Wide::splat(INFINITY)).bitmask() & ((1 << live_lanes) - 1)
+    }
+
+    /// Tests the ray against the `LANES` spheres starting at `start`. Like `Sphere::hit`, roots on either end of
+    /// `t_min..=t_max` count. Lanes that miss are set to infinity.
+    fn hit_lanes(&self, start: usize, ray: &RayLanes, t_min: Num, t_max: Num) -> Wide {
+        let end = start + LANES;
+        let ocx = ray.ox - Wide::load(&self.center_x[start..end]);
+        let ocy = ray.oy - Wide::load(&self.center_y[start..end]);
+        let ocz = ray.oz - Wide::load(&self.center_z[start..end]);
+        let radius = Wide::load(&self.radius[start..end]);
+        let a = ray.a;
+
+        let half_b = ocx * ray.dx + ocy * ray.dy + ocz * ray.dz;
+        let c = ocx * ocx + ocy * ocy + ocz * ocz - radius * radius;
+        let discriminant = half_b * half_b - a * c;
+
+        let has_roots = discriminant.ge(Wide::splat(0.));
+        let sqrtd = discriminant.max(Wide::splat(0.)).sqrt();
+        let t_min = Wide::splat(t_min);
+        let t_max = Wide::splat(t_max);
+
+        // Find nearest root in the range
+        let near = (-half_b - sqrtd) / a;
+        let far = (-half_b + sqrtd) / a;
+        let near_valid = near.ge(t_min).and(near.le(t_max));
+        let far_valid = far.ge(t_min).and(far.le(t_max));
+
+        let miss = Wide::splat(INFINITY);
+        let t = Wide::select(near_valid, near, Wide::select(far_valid, far, miss));
+
+        Wide::select(has_roots.and(near_valid.or(far_valid)), t, miss)
+    }
+}
+
+impl SpherePack {
+    /// Same as `hit`, but also returns the index of the sphere that was hit.
+    /// Adds the number of intersection tests performed to `tests`, counting each set of lanes as one.
+    pub fn hit_index(
+        &self,
+        ray: &Ray,
+        t_min: Num,
+        t_max: Num,
+        tests: &mut u32,
+    ) -> Option<(usize, HitRecord)> {
+        let (i, t) = self.closest(ray, t_min, t_max, tests)?;
+
+        let center = Point3::new(self.center_x[i], self.center_y[i], self.center_z[i]);
+        let radius = self.radius[i];
+        let (point, outward_normal) = sphere::surface_point(center, radius, ray, t);
+        let uv = sphere::sphere_uv(outward_normal);
+
+        let rec = HitRecord::new(point, ray, outward_normal, t, uv, self.materials[i]);
+
+        Some((i, rec))
+    }
+}
+
+impl Hittable for SpherePack {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        self.hit_index(ray, t_min, t_max, &mut 0)
+            .map(|(_, rec)| rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sphere_pack_matches_spheres() {
+        // Enough spheres to fill more than one set of lanes, with the last set partly padding
+        let spheres: Vec<Sphere> = (0..11)
+            .map(|i| {
+                let i = i as Num;
+                let center = Point3::new(i * 0.6 - 3., (i * 1.3).sin(), -2. - (i * 0.7).cos());
+                Sphere::new(center, 0.2 + 0.05 * i, test_material())
+            })
+            .collect();
+
+        let mut pack = SpherePack::new();
+        for sphere in spheres.iter() {
+            pack.push(*sphere);
+        }
+
+        for i in 0..200 {
+            let x = (i % 20) as Num * 0.35 - 3.5;
+            let y = (i / 20) as Num * 0.25 - 1.2;
+            // Every other ray starts inside the first sphere, so only its far root is in range
+            let origin = if i % 2 == 0 {
+                Point3::default()
+            } else {
+                spheres[0].center()
+            };
+            let ray = Ray::new(origin, Vec3::new(x, y, -2.));
+
+            let expected = spheres
+                .iter()
+                .enumerate()
+                .filter_map(|(i, sphere)| sphere.hit(&ray, 0.001, INFINITY).map(|rec| (i, rec.t)))
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            let hit = pack
+                .hit_index(&ray, 0.001, INFINITY, &mut 0)
+                .map(|(i, rec)| (i, rec.t));
+
+            assert_eq!(expected.map(|(i, _)| i), hit.map(|(i, _)| i));
+            if let (Some((_, expected)), Some((_, t))) = (expected, hit) {
+                assert!((expected - t).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn sphere_pack_matches_spheres_on_range_ends() {
+        // The near root is exactly 2 and the far root exactly 4
+        let sphere = Sphere::new(Point3::new(0., 0., -3.), 1., test_material());
+        let mut pack = SpherePack::new();
+        pack.push(sphere);
+        let ray = Ray::new(Point3::default(), Vec3::new(0., 0., -1.));
+
+        for (t_min, t_max, expected) in [
+            (0.001, 2., Some(2.)),
+            (2., 3., Some(2.)),
+            (3., 4., Some(4.)),
+            (0.001, 1.999, None),
+            (4.001, INFINITY, None),
+        ]
+        .iter()
+        {
+            let scalar = sphere.hit(&ray, *t_min, *t_max).map(|rec| rec.t);
+            let packed = pack
+                .hit_index(&ray, *t_min, *t_max, &mut 0)
+                .map(|(_, rec)| rec.t);
+
+            assert_eq!(*expected, scalar);
+            assert_eq!(*expected, packed);
+        }
+    }
+}
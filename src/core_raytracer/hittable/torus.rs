@@ -0,0 +1,288 @@
+// The torus is a quartic surface. Its roots are found by splitting the ray where the polynomial's derivative is zero,
+// which leaves pieces where it only rises or falls, so each holds at most one root that can be found by bisection.
+// This holds up in single precision, where the closed form solution doesn't.
+
+use super::{
+    frame::{around_axis, Frame, LocalHit},
+    *,
+};
+
+/// The highest degree polynomial the root finder handles.
+const MAX_DEGREE: usize = 4;
+/// The most halvings used to narrow down a root.
+const MAX_BISECTIONS: u32 = 64;
+
+/// A ring around the frame's axis, centered on its origin.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Torus {
+    frame: Frame,
+    /// Distance from the center to the middle of the tube
+    major_radius: Num,
+    /// Radius of the tube
+    minor_radius: Num,
+    material: Material,
+}
+
+impl Torus {
+    pub fn new(frame: Frame, major_radius: Num, minor_radius: Num, material: Material) -> Self {
+        Self {
+            frame,
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        let local = self.frame.local_ray(ray);
+        let speed = local.direction().len();
+        if speed <= 0. {
+            return None;
+        }
+
+        // Solved in double precision along a unit direction, starting from where the ray enters the bounding sphere
+        let v = |v: Vec3| [v.x as f64, v.y as f64, v.z as f64];
+        let d = v(local.direction() / speed);
+        let o = v(local.origin());
+        let big_r = self.major_radius as f64;
+        let small_r = self.minor_radius as f64;
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+
+        let bounds = big_r + small_r;
+        let od = dot(o, d);
+        let discriminant = od * od - (dot(o, o) - bounds * bounds);
+        if discriminant < 0. {
+            return None;
+        }
+        let start = (-od - discriminant.sqrt()).max(t_min as f64 * speed as f64);
+        let end = (-od + discriminant.sqrt()).min(t_max as f64 * speed as f64);
+        if start > end {
+            return None;
+        }
+
+        // `(|p|² + R² - r²)² = 4R² (x² + z²)`, along the ray from `start`
+        let o = [
+            o[0] + start * d[0],
+            o[1] + start * d[1],
+            o[2] + start * d[2],
+        ];
+        let od = dot(o, d);
+        let k = dot(o, o) + big_r * big_r - small_r * small_r;
+        let r2 = 4. * big_r * big_r;
+        let coeffs = [
+            k * k - r2 * (o[0] * o[0] + o[2] * o[2]),
+            4. * od * k - 2. * r2 * (o[0] * d[0] + o[2] * d[2]),
+            2. * k + 4. * od * od - r2 * (d[0] * d[0] + d[2] * d[2]),
+            4. * od,
+            1.,
+        ];
+
+        let distance = start + first_root(&coeffs, 0., end - start)?;
+        let t = (distance / speed as f64) as Num;
+
+        // The normal points away from the nearest point on the ring through the middle of the tube
+        let p = local.at(t);
+        let across = (p.x * p.x + p.z * p.z).sqrt();
+        let ring = if across > 0. {
+            Vec3::new(p.x, 0., p.z) * (self.major_radius / across)
+        } else {
+            Vec3::new(self.major_radius, 0., 0.)
+        };
+        let tube = p.y.atan2(across - self.major_radius);
+
+        let hit = LocalHit {
+            t,
+            normal: p - ring,
+            uv: (around_axis(p), (tube + PI) / (2. * PI)),
+        };
+
+        Some(self.frame.hit_record(ray, hit, self.material))
+    }
+}
+
+/// The smallest root of the polynomial in `lo..hi`. Coefficients are lowest degree first.
+fn first_root(coeffs: &[f64], lo: f64, hi: f64) -> Option<f64> {
+    let (found, len) = roots(coeffs, lo, hi);
+    if len > 0 {
+        Some(found[0])
+    } else {
+        None
+    }
+}
+
+/// The real roots of the polynomial in `lo..hi`, smallest first, and how many there are.
+fn roots(coeffs: &[f64], lo: f64, hi: f64) -> ([f64; MAX_DEGREE], usize) {
+    let mut found = [0.; MAX_DEGREE];
+    let mut len = 0;
+    let degree = coeffs.len() - 1;
+
+    if degree == 1 {
+        if coeffs[1] != 0. {
+            let root = -coeffs[0] / coeffs[1];
+            if lo <= root && root <= hi {
+                found[0] = root;
+                len = 1;
+            }
+        }
+
+        return (found, len);
+    }
+
+    // Between the roots of the derivative, the polynomial only rises or only falls
+    let mut derivative = [0.; MAX_DEGREE];
+    for i in 1..=degree {
+        derivative[i - 1] = coeffs[i] * i as f64;
+    }
+    let (critical, critical_len) = roots(&derivative[..degree], lo, hi);
+
+    let mut start = lo;
+    for &end in critical[..critical_len].iter().chain(std::iter::once(&hi)) {
+        if let Some(root) = bisect(coeffs, start, end) {
+            // A root on a critical point is found by the pieces on both sides of it
+            if len == 0 || root > found[len - 1] {
+                found[len] = root;
+                len += 1;
+            }
+        }
+        start = end;
+    }
+
+    (found, len)
+}
+
+/// The root in `lo..hi`, for a polynomial that only rises or falls in that range.
+fn bisect(coeffs: &[f64], mut lo: f64, mut hi: f64) -> Option<f64> {
+    let mut f_lo = evaluate(coeffs, lo);
+    let f_hi = evaluate(coeffs, hi);
+
+    if f_lo == 0. {
+        return Some(lo);
+    }
+    if f_hi == 0. {
+        return Some(hi);
+    }
+    if f_lo.signum() == f_hi.signum() {
+        return None;
+    }
+
+    for _ in 0..MAX_BISECTIONS {
+        let mid = 0.5 * (lo + hi);
+        if mid <= lo || mid >= hi {
+            break;
+        }
+
+        let f_mid = evaluate(coeffs, mid);
+        if f_mid == 0. {
+            return Some(mid);
+        }
+
+        if f_mid.signum() == f_lo.signum() {
+            lo = mid;
+            f_lo = f_mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    Some(0.5 * (lo + hi))
+}
+
+fn evaluate(coeffs: &[f64], x: f64) -> f64 {
+    coeffs.iter().rev().fold(0., |acc, c| acc * x + c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(expected: &[f64], (found, len): ([f64; MAX_DEGREE], usize)) {
+        assert_eq!(expected.len(), len, "{:?}", &found[..len]);
+        for (expected, root) in expected.iter().zip(found.iter()) {
+            assert!((expected - root).abs() < 1e-9, "{:?}", &found[..len]);
+        }
+    }
+
+    /// Ring radius 1 and tube radius 0.25, around the origin.
+    fn torus() -> Torus {
+        let frame = Frame::new(Point3::default(), Vec3::unit_y());
+        Torus::new(frame, 1., 0.25, test_material())
+    }
+
+    #[test]
+    fn torus_hit_outside() {
+        let ray = Ray::new(Point3::new(0., 0., 3.), Vec3::new(0., 0., -1.));
+        let rec = torus().hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(1.75, rec.t);
+        assert!(rec.front_face);
+        assert_vec_close(Vec3::new(0., 0., 1.), rec.normal);
+        assert_close(0.25, rec.u);
+        assert_close(0.5, rec.v);
+
+        let ray = Ray::new(Point3::new(1., 2., 0.), Vec3::new(0., -1., 0.));
+        let rec = torus().hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(1.75, rec.t);
+        assert_vec_close(Vec3::new(0., 1., 0.), rec.normal);
+        assert_close(0.5, rec.u);
+        assert_close(0.75, rec.v);
+    }
+
+    #[test]
+    fn torus_hole_misses() {
+        for x in [0., 0.5, -0.7].iter() {
+            let ray = Ray::new(Point3::new(*x, 2., 0.), Vec3::new(0., -1., 0.));
+            assert!(torus().hit(&ray, 0.001, INFINITY).is_none());
+        }
+    }
+
+    #[test]
+    fn torus_seen_from_inside() {
+        // From the middle of the tube, and then on across the hole to the far side
+        let ray = Ray::new(Point3::new(1., 0., 0.), Vec3::new(-1., 0., 0.));
+        let rec = torus().hit(&ray, 0.001, INFINITY).unwrap();
+        assert_close(0.25, rec.t);
+        assert!(!rec.front_face);
+        assert_vec_close(Vec3::new(1., 0., 0.), rec.normal);
+
+        let rec = torus().hit(&ray, 0.5, INFINITY).unwrap();
+        assert_close(1.75, rec.t);
+        assert!(rec.front_face);
+        assert_vec_close(Vec3::new(1., 0., 0.), rec.normal);
+    }
+
+    #[test]
+    fn roots_of_quartic() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let quartic = [24., -50., 35., -10., 1.];
+
+        assert_roots(&[1., 2., 3., 4.], roots(&quartic, 0., 5.));
+        assert_roots(&[3., 4.], roots(&quartic, 2.5, 5.));
+        assert_roots(&[], roots(&quartic, 4.5, 5.));
+        assert!((first_root(&quartic, 1.5, 5.).unwrap() - 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn roots_on_critical_points() {
+        // (x - 1)² (x - 3), which touches zero at 1 without crossing it
+        assert_roots(&[1., 3.], roots(&[-3., 7., -5., 1.], 0., 4.));
+
+        // x³, which crosses zero where it's flat
+        assert_roots(&[0.], roots(&[0., 0., 0., 1.], -1., 1.));
+
+        // (x - 2)² (x + 1)², touching zero twice, as a ray grazing both sides of a torus does
+        assert_roots(&[-1., 2.], roots(&[4., 4., -3., -2., 1.], -3., 3.));
+
+        // (x - 0.3)² (x + 0.7)², where rounding may split each root in two, but no further apart than the error
+        let first = first_root(&[0.0441, -0.168, -0.26, 0.8, 1.], -3., 3.).unwrap();
+        assert!((first + 0.7).abs() < 1e-6);
+        let first = first_root(&[0.0441, -0.168, -0.26, 0.8, 1.], 0., 3.).unwrap();
+        assert!((first - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn roots_of_linear() {
+        assert_roots(&[2.], roots(&[-4., 2.], 0., 5.));
+        assert_roots(&[], roots(&[1., 0.], 0., 5.));
+    }
+}
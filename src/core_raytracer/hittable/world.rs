@@ -1,29 +1,214 @@
-use super::*;
+use super::{sphere::Sphere, sphere_pack::SpherePack, *};
+use crate::core_raytracer::{debug::TraceStats, simd::LANES};
+
+/// Identifies an object in the world. Stays valid while other objects are added and removed,
+/// and never refers to a different object once its own is removed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectHandle {
+    slot: u32,
+    /// Bumped every time the slot is emptied, so old handles stop matching
+    generation: u32,
+}
+
+/// A borrowed object in the world.
+pub enum Object<'a> {
+    Sphere(Sphere),
+    Item(&'a dyn Hittable),
+}
+
+/// Where an object is stored.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Location {
+    /// Index into `spheres`
+    Sphere(usize),
+    /// Index into `items`
+    Item(usize),
+}
+
+struct Slot {
+    generation: u32,
+    /// `None` if the slot is free
+    location: Option<Location>,
+}
+
 pub struct World {
+    /// Spheres are kept separately, so they can be tested several at a time
+    spheres: SpherePack,
+    /// The slot of each sphere
+    sphere_slots: Vec<u32>,
     items: Vec<Box<dyn Hittable>>,
+    /// The slot of each item
+    item_slots: Vec<u32>,
+
+    slots: Vec<Slot>,
+    free_slots: Vec<u32>,
 }
 
 impl World {
     pub fn new() -> Self {
-        Self { items: vec![] }
+        Self {
+            spheres: SpherePack::new(),
+            sphere_slots: vec![],
+            items: vec![],
+            item_slots: vec![],
+            slots: vec![],
+            free_slots: vec![],
+        }
     }
 
-    pub fn add(&mut self, item: Box<dyn Hittable>) {
+    pub fn add(&mut self, item: Box<dyn Hittable>) -> ObjectHandle {
+        let handle = self.insert(Location::Item(self.items.len()));
         self.items.push(item);
+        self.item_slots.push(handle.slot);
+
+        handle
     }
-}
 
-impl Hittable for World {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let mut rec = None;
-        let mut closest_so_far = t_max;
+    pub fn add_sphere(&mut self, sphere: Sphere) -> ObjectHandle {
+        let handle = self.insert(Location::Sphere(self.spheres.len()));
+        self.spheres.push(sphere);
+        self.sphere_slots.push(handle.slot);
+
+        handle
+    }
+
+    /// Removes the object. Returns false if it was already removed.
+    pub fn remove(&mut self, handle: ObjectHandle) -> bool {
+        let location = match self.location(handle) {
+            Some(location) => location,
+            None => return false,
+        };
+
+        // The last object of the same kind is moved into the gap, so its slot has to follow it
+        let moved = match location {
+            Location::Sphere(i) => {
+                self.spheres.swap_remove(i);
+                self.sphere_slots.swap_remove(i);
+                self.sphere_slots
+                    .get(i)
+                    .map(|slot| (*slot, Location::Sphere(i)))
+            }
+            Location::Item(i) => {
+                self.items.swap_remove(i);
+                self.item_slots.swap_remove(i);
+                self.item_slots
+                    .get(i)
+                    .map(|slot| (*slot, Location::Item(i)))
+            }
+        };
+
+        if let Some((slot, location)) = moved {
+            self.slots[slot as usize].location = Some(location);
+        }
+
+        let slot = &mut self.slots[handle.slot as usize];
+        slot.location = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.slot);
+
+        true
+    }
+
+    /// Whether the object is still in the world.
+    pub fn contains(&self, handle: ObjectHandle) -> bool {
+        self.location(handle).is_some()
+    }
+
+    pub fn get(&self, handle: ObjectHandle) -> Option<Object<'_>> {
+        self.location(handle).map(|location| self.object(location))
+    }
 
-        for hittable in &self.items {
-            match hittable.hit(ray, t_min, t_max) {
+    /// Moves, resizes or recolors the sphere. Returns false if the object was removed or isn't a sphere.
+    pub fn set_sphere(&mut self, handle: ObjectHandle, sphere: Sphere) -> bool {
+        match self.location(handle) {
+            Some(Location::Sphere(i)) => {
+                self.spheres.set(i, sphere);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replaces the item, keeping its handle. Returns false if the object was removed or is a sphere.
+    pub fn replace(&mut self, handle: ObjectHandle, item: Box<dyn Hittable>) -> bool {
+        match self.location(handle) {
+            Some(Location::Item(i)) => {
+                self.items[i] = item;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Every object in the world, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (ObjectHandle, Object<'_>)> {
+        self.slots.iter().enumerate().filter_map(move |(slot, s)| {
+            let handle = ObjectHandle {
+                slot: slot as u32,
+                generation: s.generation,
+            };
+
+            s.location.map(|location| (handle, self.object(location)))
+        })
+    }
+
+    /// The number of items in the world
+    pub fn len(&self) -> usize {
+        self.spheres.len() + self.items.len()
+    }
+
+    /// The most intersection tests a single `hit` can perform. Spheres are tested a set of lanes at a time.
+    pub fn max_tests(&self) -> u32 {
+        let sphere_batches = (self.spheres.len() + LANES - 1) / LANES;
+        (sphere_batches + self.items.len()) as u32
+    }
+
+    /// Same as `hit`, but records the number of intersection tests performed.
+    pub fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t_min: Num,
+        t_max: Num,
+        stats: &mut TraceStats,
+    ) -> Option<HitRecord> {
+        self.hit_counted(ray, t_min, t_max, &mut stats.tests)
+            .map(|(_, rec)| rec)
+    }
+
+    /// Same as `hit`, but also returns the object that was hit.
+    pub fn hit_object(
+        &self,
+        ray: &Ray,
+        t_min: Num,
+        t_max: Num,
+    ) -> Option<(ObjectHandle, HitRecord)> {
+        self.hit_counted(ray, t_min, t_max, &mut 0)
+    }
+
+    /// Same as `hit_object`, but adds the number of intersection tests performed to `tests`.
+    fn hit_counted(
+        &self,
+        ray: &Ray,
+        t_min: Num,
+        t_max: Num,
+        tests: &mut u32,
+    ) -> Option<(ObjectHandle, HitRecord)> {
+        let mut rec = self
+            .spheres
+            .hit_index(ray, t_min, t_max, tests)
+            .map(|(i, hr)| (self.handle(self.sphere_slots[i]), hr));
+        let mut closest_so_far = match &rec {
+            Some((_, hr)) => hr.t,
+            None => t_max,
+        };
+
+        for (i, hittable) in self.items.iter().enumerate() {
+            *tests += 1;
+            match hittable.hit(ray, t_min, closest_so_far) {
                 Some(hr) => {
                     if closest_so_far > hr.t {
                         closest_so_far = hr.t;
-                        rec = Some(hr);
+                        rec = Some((self.handle(self.item_slots[i]), hr));
                     }
                 }
                 None => {}
@@ -32,4 +217,238 @@ impl Hittable for World {
 
         rec
     }
+
+    /// Takes a free slot for an object at the location, reusing an old one if there is one.
+    fn insert(&mut self, location: Location) -> ObjectHandle {
+        let slot = match self.free_slots.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    location: None,
+                });
+                self.slots.len() as u32 - 1
+            }
+        };
+
+        self.slots[slot as usize].location = Some(location);
+
+        self.handle(slot)
+    }
+
+    /// The current handle for the slot.
+    fn handle(&self, slot: u32) -> ObjectHandle {
+        ObjectHandle {
+            slot,
+            generation: self.slots[slot as usize].generation,
+        }
+    }
+
+    /// Where the object is stored, or `None` if the handle is stale.
+    fn location(&self, handle: ObjectHandle) -> Option<Location> {
+        let slot = self.slots.get(handle.slot as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        slot.location
+    }
+
+    fn object(&self, location: Location) -> Object<'_> {
+        match location {
+            Location::Sphere(i) => Object::Sphere(self.spheres.get(i)),
+            Location::Item(i) => Object::Item(self.items[i].as_ref()),
+        }
+    }
+}
+
+impl Hittable for World {
+    fn hit(&self, ray: &Ray, t_min: Num, t_max: Num) -> Option<HitRecord> {
+        self.hit_object(ray, t_min, t_max).map(|(_, rec)| rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sphere in front of the origin, at `x` along the X axis.
+    fn sphere_at(x: Num) -> Sphere {
+        Sphere::new(Point3::new(x, 0., -2.), 0.5, test_material())
+    }
+
+    /// The object hit looking down -Z from `x` along the X axis.
+    fn object_at(world: &World, x: Num) -> Option<ObjectHandle> {
+        let ray = Ray::new(Point3::new(x, 0., 0.), Vec3::new(0., 0., -1.));
+        world
+            .hit_object(&ray, 0.001, INFINITY)
+            .map(|(handle, _)| handle)
+    }
+
+    #[test]
+    fn world_add() {
+        let mut world = World::new();
+        let sphere = world.add_sphere(sphere_at(0.));
+        let item = world.add(Box::new(sphere_at(2.)));
+
+        assert_eq!(2, world.len());
+        assert_ne!(sphere, item);
+        assert_eq!(Some(sphere), object_at(&world, 0.));
+        assert_eq!(Some(item), object_at(&world, 2.));
+        assert_eq!(None, object_at(&world, 4.));
+    }
+
+    #[test]
+    fn world_remove() {
+        let mut world = World::new();
+        let sphere = world.add_sphere(sphere_at(0.));
+        let item = world.add(Box::new(sphere_at(2.)));
+
+        assert!(world.remove(sphere));
+        assert_eq!(1, world.len());
+        assert_eq!(None, object_at(&world, 0.));
+        assert_eq!(Some(item), object_at(&world, 2.));
+
+        assert!(world.remove(item));
+        assert_eq!(0, world.len());
+        assert_eq!(None, object_at(&world, 2.));
+    }
+
+    #[test]
+    fn world_stale_handle_after_reuse() {
+        let mut world = World::new();
+        let old = world.add_sphere(sphere_at(0.));
+        world.remove(old);
+
+        // The new object takes the old one's slot, but not its handle
+        let new = world.add_sphere(sphere_at(2.));
+        assert_ne!(old, new);
+
+        assert!(!world.remove(old));
+        assert_eq!(1, world.len());
+        assert_eq!(Some(new), object_at(&world, 2.));
+    }
+
+    #[test]
+    fn world_swap_remove_keeps_moved_handle() {
+        let mut world = World::new();
+        let first_sphere = world.add_sphere(sphere_at(0.));
+        let last_sphere = world.add_sphere(sphere_at(2.));
+        let first_item = world.add(Box::new(sphere_at(4.)));
+        let last_item = world.add(Box::new(sphere_at(6.)));
+
+        // Removing the first of each moves the last into its place
+        world.remove(first_sphere);
+        world.remove(first_item);
+
+        assert_eq!(Some(last_sphere), object_at(&world, 2.));
+        assert_eq!(Some(last_item), object_at(&world, 6.));
+
+        assert!(world.remove(last_sphere));
+        assert!(world.remove(last_item));
+        assert_eq!(0, world.len());
+    }
+
+    #[test]
+    fn world_set_sphere() {
+        let mut world = World::new();
+        let sphere = world.add_sphere(sphere_at(0.));
+        let item = world.add(Box::new(sphere_at(2.)));
+
+        // Moving keeps the handle
+        assert!(world.set_sphere(sphere, sphere_at(4.)));
+        assert_eq!(None, object_at(&world, 0.));
+        assert_eq!(Some(sphere), object_at(&world, 4.));
+        match world.get(sphere) {
+            Some(Object::Sphere(moved)) => assert_close(4., moved.center().x),
+            _ => panic!("expected a sphere"),
+        }
+
+        // Items have to be replaced instead
+        assert!(!world.set_sphere(item, sphere_at(6.)));
+        assert_eq!(None, object_at(&world, 6.));
+    }
+
+    #[test]
+    fn world_replace() {
+        let mut world = World::new();
+        let sphere = world.add_sphere(sphere_at(0.));
+        let item = world.add(Box::new(sphere_at(2.)));
+
+        assert!(world.replace(item, Box::new(sphere_at(4.))));
+        assert_eq!(None, object_at(&world, 2.));
+        assert_eq!(Some(item), object_at(&world, 4.));
+
+        // Spheres have to be set instead
+        assert!(!world.replace(sphere, Box::new(sphere_at(6.))));
+        assert_eq!(Some(sphere), object_at(&world, 0.));
+        assert_eq!(None, object_at(&world, 6.));
+    }
+
+    #[test]
+    fn world_update_with_stale_handle() {
+        let mut world = World::new();
+        let old_sphere = world.add_sphere(sphere_at(0.));
+        let old_item = world.add(Box::new(sphere_at(2.)));
+        world.remove(old_sphere);
+        world.remove(old_item);
+
+        // The new objects take the old ones' slots, and mustn't be changed through the old handles
+        let new_item = world.add(Box::new(sphere_at(4.)));
+        let new_sphere = world.add_sphere(sphere_at(6.));
+
+        assert!(!world.contains(old_sphere));
+        assert!(!world.contains(old_item));
+        assert!(!world.set_sphere(old_sphere, sphere_at(8.)));
+        assert!(!world.set_sphere(old_item, sphere_at(8.)));
+        assert!(!world.replace(old_sphere, Box::new(sphere_at(8.))));
+        assert!(!world.replace(old_item, Box::new(sphere_at(8.))));
+        assert!(world.get(old_sphere).is_none());
+
+        assert_eq!(None, object_at(&world, 8.));
+        assert_eq!(Some(new_item), object_at(&world, 4.));
+        assert_eq!(Some(new_sphere), object_at(&world, 6.));
+    }
+
+    #[test]
+    fn world_iter() {
+        let mut world = World::new();
+        let removed = world.add_sphere(sphere_at(0.));
+        let sphere = world.add_sphere(sphere_at(2.));
+        let item = world.add(Box::new(sphere_at(4.)));
+        world.remove(removed);
+
+        let mut spheres = vec![];
+        let mut items = vec![];
+        for (handle, object) in world.iter() {
+            match object {
+                Object::Sphere(s) => spheres.push((handle, s.center().x)),
+                Object::Item(_) => items.push(handle),
+            }
+        }
+
+        assert_eq!(1, spheres.len());
+        assert_eq!(sphere, spheres[0].0);
+        assert_close(2., spheres[0].1);
+        assert_eq!(vec![item], items);
+    }
+
+    #[test]
+    fn world_counts_tests() {
+        let mut world = World::new();
+        for i in 0..LANES + 1 {
+            world.add_sphere(sphere_at(i as Num * 2.));
+        }
+        world.add(Box::new(sphere_at(-2.)));
+        world.add(Box::new(sphere_at(-4.)));
+
+        // Two sets of lanes for the spheres, and one test for each item, whether anything is hit or not
+        assert_eq!(4, world.max_tests());
+        for x in [0., -4., 100.].iter() {
+            let ray = Ray::new(Point3::new(*x, 0., 0.), Vec3::new(0., 0., -1.));
+            let mut stats = TraceStats::default();
+            world.hit_with_stats(&ray, 0.001, INFINITY, &mut stats);
+            assert_eq!(4, stats.tests);
+        }
+    }
 }
@@ -0,0 +1,190 @@
+use super::{
+    debug::TraceStats,
+    hittable::World,
+    material::Lobe,
+    num::Num,
+    ray::Ray,
+    rng, spectral,
+    vec3::{Color, Vec3},
+    MAX_DRAW, MIN_DRAW,
+};
+
+/// The highest survival probability Russian roulette will use. Keeps even bright paths from living forever.
+const MAX_SURVIVAL_PROBABILITY: Num = 0.95;
+
+/// How light is carried along paths.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IntegratorMode {
+    /// Paths carry RGB colors
+    Rgb,
+    /// Each path carries a single sampled wavelength. Enables dispersion.
+    Spectral,
+}
+
+/// Limits on how many times a path may bounce.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BounceLimits {
+    /// Max number of diffuse bounces
+    pub diffuse: u32,
+    /// Max number of specular (reflective) bounces
+    pub specular: u32,
+    /// Max number of transmission (refractive) bounces
+    pub transmission: u32,
+    /// Max number of bounces of any kind
+    pub total: u32,
+    /// The number of bounces before Russian roulette may terminate a path
+    pub roulette_depth: u32,
+}
+
+/// The number of bounces a path has taken so far.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct BounceCounts {
+    diffuse: u32,
+    specular: u32,
+    transmission: u32,
+    total: u32,
+}
+
+impl BounceCounts {
+    /// Attempts to record a bounce of the given lobe. Returns false if a limit was hit.
+    fn try_bounce(&mut self, lobe: Lobe, limits: &BounceLimits) -> bool {
+        let (count, limit) = match lobe {
+            Lobe::Diffuse => (&mut self.diffuse, limits.diffuse),
+            Lobe::Specular => (&mut self.specular, limits.specular),
+            Lobe::Transmission => (&mut self.transmission, limits.transmission),
+        };
+
+        if *count >= limit || self.total >= limits.total {
+            return false;
+        }
+
+        *count += 1;
+        self.total += 1;
+
+        true
+    }
+}
+
+/// Traces a path through the world, returning the RGB light carried back along the ray.
+pub fn ray_color(
+    ray: &Ray,
+    world: &World,
+    limits: &BounceLimits,
+    mode: IntegratorMode,
+    stats: &mut TraceStats,
+) -> Color {
+    match mode {
+        IntegratorMode::Rgb => trace_path(ray, world, limits, None, stats),
+        IntegratorMode::Spectral => {
+            let wavelength = spectral::sample_wavelength(rng::random());
+            let ray = ray.with_wavelength(wavelength);
+
+            // Spectral paths carry the same value in every channel
+            let radiance = trace_path(&ray, world, limits, Some(wavelength), stats).x;
+            spectral::to_rgb(radiance, wavelength)
+        }
+    }
+}
+
+/// Iteratively traces a path through the world.
+/// If a wavelength is given, all colors are converted to that wavelength as the path is traced.
+fn trace_path(
+    ray: &Ray,
+    world: &World,
+    limits: &BounceLimits,
+    wavelength: Option<Num>,
+    stats: &mut TraceStats,
+) -> Color {
+    let to_path_space = |color: Color| match wavelength {
+        Some(wavelength) => {
+            let v = spectral::from_rgb(color, wavelength);
+            Color::new(v, v, v)
+        }
+        None => color,
+    };
+
+    let mut ray = *ray;
+    let mut throughput = Color::new(1., 1., 1.);
+    let mut counts = BounceCounts::default();
+
+    loop {
+        let rec = match world.hit_with_stats(&ray, MIN_DRAW, MAX_DRAW, stats) {
+            Some(rec) => rec,
+            None => return throughput * to_path_space(sky_color(&ray)),
+        };
+
+        let sample = match rec.material.sample(&ray, &rec) {
+            Some(sample) => sample,
+            None => return Color::default(),
+        };
+
+        if !counts.try_bounce(sample.lobe, limits) {
+            return Color::default();
+        }
+
+        stats.bounces += 1;
+        throughput = throughput * to_path_space(sample.weight(rec.normal));
+        ray = match wavelength {
+            Some(wavelength) => sample.ray.with_wavelength(wavelength),
+            None => sample.ray,
+        };
+
+        if counts.total >= limits.roulette_depth {
+            throughput = match roulette(throughput, rng::random()) {
+                Some(throughput) => throughput,
+                None => return Color::default(),
+            };
+        }
+    }
+}
+
+/// Russian roulette. Paths that carry little light are likely to be terminated, while survivors are boosted to keep
+/// the estimate unbiased. Returns the boosted throughput if the path survives the `random` draw in `0.0..1.0`.
+fn roulette(throughput: Color, random: Num) -> Option<Color> {
+    // A throughput that isn't finite would poison the pixel and everything accumulated into it, and can hide from
+    // `max_component`, as `max` skips NaN
+    if !throughput.is_finite() {
+        return None;
+    }
+
+    let survival = throughput.max_component().min(MAX_SURVIVAL_PROBABILITY);
+    if survival <= 0. || random > survival {
+        return None;
+    }
+
+    Some(throughput / survival)
+}
+
+/// The color of the sky in the direction of the ray.
+fn sky_color(ray: &Ray) -> Color {
+    let unit_dir: Vec3 = ray.direction().unit_vector();
+    let t = 0.5 * (unit_dir.y + 1.);
+    (1. - t) * Color::new(1., 1., 1.) + t * Color::new(0.5, 0.7, 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roulette_boosts_survivors() {
+        let throughput = Color::new(0.5, 0.25, 0.1);
+
+        let boosted = roulette(throughput, 0.4).unwrap();
+        assert!((boosted.x - 1.).abs() < 1e-6);
+        assert!((boosted.y - 0.5).abs() < 1e-6);
+        assert_eq!(None, roulette(throughput, 0.6));
+        assert_eq!(None, roulette(Color::default(), 0.));
+    }
+
+    #[test]
+    fn roulette_drops_throughput_that_isnt_finite() {
+        // A bright path would always survive, and be boosted
+        assert!(roulette(Color::new(10., 10., 10.), 0.).is_some());
+
+        assert_eq!(None, roulette(Color::new(Num::NAN, 0.5, 0.5), 0.));
+        assert_eq!(None, roulette(Color::new(0.5, Num::NAN, 0.5), 0.));
+        assert_eq!(None, roulette(Color::new(0.5, 0.5, Num::INFINITY), 0.));
+        assert_eq!(None, roulette(Color::new(-Num::INFINITY, 0.5, 0.5), 0.));
+    }
+}
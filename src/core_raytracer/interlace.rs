@@ -0,0 +1,33 @@
+/// Which pixels are traced each frame. Pixels that aren't traced keep their color from a previous frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interlace {
+    /// Every pixel is traced every frame
+    Off,
+    /// Alternating pixels are traced every other frame
+    Checkerboard,
+    /// Every Nth row is traced, cycling through all rows over N frames
+    Rows(u32),
+}
+
+impl Interlace {
+    /// Whether the pixel is traced on the given frame.
+    pub fn is_traced(&self, x: u32, y: u32, frame: u64) -> bool {
+        match *self {
+            Interlace::Off => true,
+            Interlace::Checkerboard => (x as u64 + y as u64 + frame) % 2 == 0,
+            Interlace::Rows(n) => {
+                let n = n.max(1) as u64;
+                y as u64 % n == frame % n
+            }
+        }
+    }
+
+    /// The next mode, for cycling through them with a hotkey.
+    pub fn next(&self) -> Self {
+        match self {
+            Interlace::Off => Interlace::Checkerboard,
+            Interlace::Checkerboard => Interlace::Rows(4),
+            Interlace::Rows(_) => Interlace::Off,
+        }
+    }
+}
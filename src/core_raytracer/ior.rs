@@ -0,0 +1,63 @@
+use super::num::Num;
+
+/// Wavelength, in nanometers, used when the IOR is needed for non spectral rendering.
+/// The sodium D line, which is what IORs are usually quoted at.
+const NOMINAL_WAVELENGTH: Num = 587.6;
+
+/// Index of refraction, optionally varying with wavelength.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Ior {
+    /// Same IOR for all wavelengths
+    Constant(Num),
+    /// Cauchy's equation, `n = a + b / λ²` with `λ` in micrometers
+    Cauchy { a: Num, b: Num },
+    /// Sellmeier equation, `n² = 1 + Σ bᵢλ² / (λ² - cᵢ)` with `λ` in micrometers
+    Sellmeier { b: [Num; 3], c: [Num; 3] },
+}
+
+impl Ior {
+    /// Schott N-BK7 crown glass
+    pub fn bk7() -> Self {
+        Ior::Sellmeier {
+            b: [1.039_612_1, 0.231_792_34, 1.010_469_5],
+            c: [0.006_000_699, 0.020_017_914, 103.560_65],
+        }
+    }
+
+    /// Diamond, which is highly dispersive
+    pub fn diamond() -> Self {
+        Ior::Sellmeier {
+            b: [0.3306, 4.3356, 0.],
+            c: [0.030_625, 0.011_236, 0.],
+        }
+    }
+
+    /// Returns the IOR for the wavelength, in nanometers. `None` uses the nominal wavelength.
+    pub fn at(&self, wavelength: Option<Num>) -> Num {
+        let wavelength = wavelength.unwrap_or(NOMINAL_WAVELENGTH);
+        let um = wavelength / 1000.;
+        let um2 = um * um;
+
+        match self {
+            Ior::Constant(ior) => *ior,
+            Ior::Cauchy { a, b } => a + b / um2,
+            Ior::Sellmeier { b, c } => {
+                let mut n2 = 1.;
+                for i in 0..3 {
+                    n2 += b[i] * um2 / (um2 - c[i]);
+                }
+
+                n2.max(1.).sqrt()
+            }
+        }
+    }
+
+    /// The coefficients that define the IOR
+    pub fn params(&self) -> Vec<Num> {
+        match self {
+            Ior::Constant(ior) => vec![*ior],
+            Ior::Cauchy { a, b } => vec![*a, *b],
+            Ior::Sellmeier { b, c } => vec![b[0], b[1], b[2], c[0], c[1], c[2]],
+        }
+    }
+}
@@ -1,10 +1,57 @@
 use super::{
+    conductor::{fresnel_conductor, Conductor},
     hittable::HitRecord,
+    ior::Ior,
+    microfacet,
+    num::{Num, EPSILON, PI},
+    onb::Onb,
     ray::Ray,
     rng,
     vec3::{Color, Vec3},
 };
 
+/// Roughness below this is treated as a perfect mirror.
+const SMOOTH_ROUGHNESS: Num = 0.01;
+
+/// The kind of interaction a scattered ray came from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Lobe {
+    Diffuse,
+    Specular,
+    Transmission,
+}
+
+/// A direction sampled from a material's BSDF.
+pub struct BsdfSample {
+    /// The scattered ray
+    pub ray: Ray,
+    /// The BSDF value for the sampled direction.
+    /// For delta lobes this is the full path weight, as the BSDF can't be evaluated for them.
+    pub value: Color,
+    /// The probability density of sampling the direction, with respect to solid angle.
+    /// Meaningless for delta lobes.
+    pub pdf: Num,
+    pub lobe: Lobe,
+    /// Whether the sample came from a perfectly specular (delta) distribution
+    pub is_delta: bool,
+}
+
+impl BsdfSample {
+    /// The factor the path throughput should be multiplied by, `value * cos / pdf`.
+    pub fn weight(&self, normal: Vec3) -> Color {
+        if self.is_delta {
+            return self.value;
+        }
+
+        if self.pdf <= 0. {
+            return Color::default();
+        }
+
+        let cos_theta = self.ray.direction().unit_vector().dot(normal).abs();
+        self.value * (cos_theta / self.pdf)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Material {
     Lambertian {
@@ -12,30 +59,120 @@ pub enum Material {
     },
     Metal {
         albedo: Color,
-        fuzz: f32,
+        fuzz: Num,
     },
     Dielectric {
         /// Index of refraction
-        ior: f32,
+        ior: Ior,
+        /// Beer-Lambert absorption coefficient per unit of distance travelled inside the medium.
+        /// Black is perfectly clear.
+        absorption: Color,
+    },
+    /// GGX microfacet material following the glTF metallic-roughness model
+    Pbr {
+        base_color: Color,
+        /// `0.0` is a dielectric, `1.0` is a metal
+        metallic: Num,
+        /// Perceptual roughness, `0.0..1.0`
+        roughness: Num,
+        /// Specular reflectance for dielectrics. `0.5` maps to the common 4% reflectance.
+        specular: Num,
+    },
+    /// Metal driven by a complex index of refraction
+    Conductor {
+        /// Real part of the index of refraction
+        eta: Color,
+        /// Extinction coefficient
+        k: Color,
+        /// Perceptual roughness, `0.0..1.0`. Near zero is a perfect mirror.
+        roughness: Num,
     },
 }
 
 impl Material {
-    pub fn scatter(&self, ray: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+    /// Creates a conductor from one of the metal presets.
+    pub fn conductor(preset: Conductor, roughness: Num) -> Self {
+        Material::Conductor {
+            eta: preset.eta(),
+            k: preset.k(),
+            roughness,
+        }
+    }
+
+    /// Creates a dielectric that has been tinted to `color` after light travels `distance` through it.
+    pub fn tinted_dielectric(ior: Ior, color: Color, distance: Num) -> Self {
+        let distance = distance.max(EPSILON);
+        let channel = |c: Num| -c.max(EPSILON).min(1.).ln() / distance;
+
+        Material::Dielectric {
+            ior,
+            absorption: Color::new(channel(color.x), channel(color.y), channel(color.z)),
+        }
+    }
+
+    /// Returns an id derived from the material's type and parameters.
+    /// Identical materials share an id.
+    pub fn id(&self) -> u32 {
+        let (kind, params): (u32, Vec<Num>) = match self {
+            Material::Lambertian { albedo } => (0, vec![albedo.x, albedo.y, albedo.z]),
+            Material::Metal { albedo, fuzz } => (1, vec![albedo.x, albedo.y, albedo.z, *fuzz]),
+            Material::Dielectric { ior, absorption } => {
+                let mut params = ior.params();
+                params.extend_from_slice(&[absorption.x, absorption.y, absorption.z]);
+                (2, params)
+            }
+            Material::Pbr {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+            } => (
+                3,
+                vec![
+                    base_color.x,
+                    base_color.y,
+                    base_color.z,
+                    *metallic,
+                    *roughness,
+                    *specular,
+                ],
+            ),
+            Material::Conductor { eta, k, roughness } => {
+                (4, vec![eta.x, eta.y, eta.z, k.x, k.y, k.z, *roughness])
+            }
+        };
+
+        // FNV-1a. Params are hashed at single precision so ids don't depend on the build's precision.
+        let mut hash: u32 = 0x811C_9DC5;
+        for word in std::iter::once(kind).chain(params.iter().map(|p| (*p as f32).to_bits())) {
+            for byte in word.to_le_bytes().iter() {
+                hash ^= *byte as u32;
+                hash = hash.wrapping_mul(0x0100_0193);
+            }
+        }
+
+        hash
+    }
+
+    /// Samples an outgoing direction for the incoming ray.
+    pub fn sample(&self, ray: &Ray, rec: &HitRecord) -> Option<BsdfSample> {
         match self {
-            Material::Lambertian { albedo } => {
-                let scatter_dir = {
-                    let scattered_dir = rec.normal + Vec3::random_unit_vector();
-                    // Handle degenerate scatter direction
-                    if scattered_dir.near_zero() {
-                        rec.normal
-                    } else {
-                        scattered_dir
-                    }
-                };
-                let scattered = Ray::new(rec.point, scatter_dir);
-                let attenuation = *albedo;
-                return Some((scattered, attenuation));
+            Material::Lambertian { .. } => {
+                let onb = Onb::from_w(rec.normal);
+                let direction = onb.local(Vec3::random_cosine_direction());
+                let scattered = rec.spawn_ray(direction);
+                let pdf = self.pdf(ray, rec, direction);
+                if pdf <= 0. {
+                    return None;
+                }
+
+                Some(BsdfSample {
+                    ray: scattered,
+                    value: self.eval(ray, rec, direction),
+                    pdf,
+                    lobe: Lobe::Diffuse,
+                    is_delta: false,
+                })
             }
             Material::Metal { albedo, fuzz } => {
                 let fuzz = {
@@ -47,39 +184,281 @@ impl Material {
                 };
 
                 let reflected = ray.direction().unit_vector().reflect(rec.normal);
-                let scattered =
-                    Ray::new(rec.point, reflected + fuzz * Vec3::random_in_unit_sphere());
-                let attenuation = *albedo;
-                if scattered.direction().dot(rec.normal) > 0. {
-                    return Some((scattered, attenuation));
+                let scattered = rec.spawn_ray(reflected + fuzz * Vec3::random_in_unit_sphere());
+                if scattered.direction().dot(rec.normal) <= 0. {
+                    return None;
                 }
+
+                Some(BsdfSample {
+                    ray: scattered,
+                    value: *albedo,
+                    pdf: 1.,
+                    lobe: Lobe::Specular,
+                    is_delta: true,
+                })
             }
-            Material::Dielectric { ior } => {
-                let attenuation: Color = (1., 1., 1.).into();
-                let refraction_ratio = if rec.front_face { 1. / ior } else { *ior };
+            Material::Dielectric { ior, absorption } => {
+                // A back face hit means the ray travelled through the medium to get here
+                let attenuation: Color = if rec.front_face {
+                    (1., 1., 1.).into()
+                } else {
+                    let distance = rec.t * ray.direction().len();
+                    (-*absorption * distance).exp()
+                };
+
+                let ior = ior.at(ray.wavelength());
+                let refraction_ratio = if rec.front_face { 1. / ior } else { ior };
 
                 let unit_dir = ray.direction().unit_vector();
                 let cos_theta = (-unit_dir).dot(rec.normal).min(1.);
                 let sin_theta = (1. - cos_theta * cos_theta).sqrt();
                 let cannot_refract = refraction_ratio * sin_theta > 1.;
 
-                let direction =
+                let (direction, lobe) =
                     if cannot_refract || reflectance(cos_theta, refraction_ratio) > rng::random() {
-                        unit_dir.reflect(rec.normal)
+                        (unit_dir.reflect(rec.normal), Lobe::Specular)
                     } else {
-                        unit_dir.refract(rec.normal, refraction_ratio)
+                        (
+                            unit_dir.refract(rec.normal, refraction_ratio),
+                            Lobe::Transmission,
+                        )
                     };
 
-                let scattered = Ray::new(rec.point, direction);
-                return Some((scattered, attenuation));
+                Some(BsdfSample {
+                    ray: rec.spawn_ray(direction),
+                    value: attenuation,
+                    pdf: 1.,
+                    lobe,
+                    is_delta: true,
+                })
+            }
+            Material::Pbr {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+            } => {
+                let onb = Onb::from_w(rec.normal);
+                let wo = onb.to_local(-ray.direction().unit_vector());
+                if wo.z <= 0. {
+                    return None;
+                }
+
+                // Pick a lobe, then sample it
+                let specular_probability =
+                    pbr_specular_probability(*base_color, *metallic, *specular);
+                let (wi, lobe) = if rng::random() < specular_probability {
+                    let alpha = microfacet::roughness_to_alpha(*roughness);
+                    let h = microfacet::sample_vndf(wo, alpha, rng::random(), rng::random());
+                    ((-wo).reflect(h), Lobe::Specular)
+                } else {
+                    (Vec3::random_cosine_direction(), Lobe::Diffuse)
+                };
+
+                if wi.z <= 0. {
+                    return None;
+                }
+
+                let direction = onb.local(wi);
+                let pdf = self.pdf(ray, rec, direction);
+                if pdf <= 0. {
+                    return None;
+                }
+
+                Some(BsdfSample {
+                    ray: rec.spawn_ray(direction),
+                    value: self.eval(ray, rec, direction),
+                    pdf,
+                    lobe,
+                    is_delta: false,
+                })
+            }
+            Material::Conductor { eta, k, roughness } => {
+                if *roughness < SMOOTH_ROUGHNESS {
+                    let unit_dir = ray.direction().unit_vector();
+                    let cos_theta = (-unit_dir).dot(rec.normal);
+
+                    return Some(BsdfSample {
+                        ray: rec.spawn_ray(unit_dir.reflect(rec.normal)),
+                        value: fresnel_conductor(cos_theta, *eta, *k),
+                        pdf: 1.,
+                        lobe: Lobe::Specular,
+                        is_delta: true,
+                    });
+                }
+
+                let onb = Onb::from_w(rec.normal);
+                let wo = onb.to_local(-ray.direction().unit_vector());
+                if wo.z <= 0. {
+                    return None;
+                }
+
+                let alpha = microfacet::roughness_to_alpha(*roughness);
+                let h = microfacet::sample_vndf(wo, alpha, rng::random(), rng::random());
+                let wi = (-wo).reflect(h);
+                if wi.z <= 0. {
+                    return None;
+                }
+
+                let direction = onb.local(wi);
+                let pdf = self.pdf(ray, rec, direction);
+                if pdf <= 0. {
+                    return None;
+                }
+
+                Some(BsdfSample {
+                    ray: rec.spawn_ray(direction),
+                    value: self.eval(ray, rec, direction),
+                    pdf,
+                    lobe: Lobe::Specular,
+                    is_delta: false,
+                })
+            }
+        }
+    }
+
+    /// Evaluates the BSDF for light arriving from `direction` and leaving along the reversed incoming ray.
+    /// Delta lobes always evaluate to black.
+    pub fn eval(&self, ray: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        match self {
+            Material::Lambertian { albedo } => {
+                if direction.dot(rec.normal) <= 0. {
+                    Color::default()
+                } else {
+                    *albedo / PI
+                }
+            }
+            Material::Metal { .. } | Material::Dielectric { .. } => Color::default(),
+            Material::Pbr {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+            } => {
+                let (wo, wi) = match local_directions(ray, rec, direction) {
+                    Some(dirs) => dirs,
+                    None => return Color::default(),
+                };
+
+                let alpha = microfacet::roughness_to_alpha(*roughness);
+                let h = (wo + wi).unit_vector();
+                let f0 = pbr_f0(*base_color, *metallic, *specular);
+                let f = microfacet::fresnel_schlick(f0, wi.dot(h));
+
+                let specular = f
+                    * (microfacet::d(h, alpha) * microfacet::g2(wo, wi, alpha)
+                        / (4. * wo.z * wi.z));
+                let diffuse = (Color::new(1., 1., 1.) - f) * (1. - metallic) * (*base_color / PI);
+
+                diffuse + specular
+            }
+            Material::Conductor { eta, k, roughness } => {
+                if *roughness < SMOOTH_ROUGHNESS {
+                    return Color::default();
+                }
+
+                let (wo, wi) = match local_directions(ray, rec, direction) {
+                    Some(dirs) => dirs,
+                    None => return Color::default(),
+                };
+
+                let alpha = microfacet::roughness_to_alpha(*roughness);
+                let h = (wo + wi).unit_vector();
+                let f = fresnel_conductor(wi.dot(h), *eta, *k);
+
+                f * (microfacet::d(h, alpha) * microfacet::g2(wo, wi, alpha) / (4. * wo.z * wi.z))
             }
         }
+    }
 
+    /// The probability density `sample` would produce `direction` with, with respect to solid angle.
+    /// Delta lobes always return 0.
+    pub fn pdf(&self, ray: &Ray, rec: &HitRecord, direction: Vec3) -> Num {
+        match self {
+            Material::Lambertian { .. } => {
+                let cos_theta = direction.unit_vector().dot(rec.normal);
+                if cos_theta <= 0. {
+                    0.
+                } else {
+                    cos_theta / PI
+                }
+            }
+            Material::Metal { .. } | Material::Dielectric { .. } => 0.,
+            Material::Pbr {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+            } => {
+                let (wo, wi) = match local_directions(ray, rec, direction) {
+                    Some(dirs) => dirs,
+                    None => return 0.,
+                };
+
+                let alpha = microfacet::roughness_to_alpha(*roughness);
+                let h = (wo + wi).unit_vector();
+                let specular_probability =
+                    pbr_specular_probability(*base_color, *metallic, *specular);
+
+                specular_probability * microfacet::reflection_pdf(wo, h, alpha)
+                    + (1. - specular_probability) * wi.z / PI
+            }
+            Material::Conductor { roughness, .. } => {
+                if *roughness < SMOOTH_ROUGHNESS {
+                    return 0.;
+                }
+
+                let (wo, wi) = match local_directions(ray, rec, direction) {
+                    Some(dirs) => dirs,
+                    None => return 0.,
+                };
+
+                let alpha = microfacet::roughness_to_alpha(*roughness);
+                let h = (wo + wi).unit_vector();
+
+                microfacet::reflection_pdf(wo, h, alpha)
+            }
+        }
+    }
+}
+
+/// Returns the outgoing (towards the viewer) and incoming directions in the local shading space of the hit.
+/// Returns `None` if either is below the surface.
+fn local_directions(ray: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Vec3, Vec3)> {
+    let onb = Onb::from_w(rec.normal);
+    let wo = onb.to_local(-ray.direction().unit_vector());
+    let wi = onb.to_local(direction.unit_vector());
+
+    if wo.z <= 0. || wi.z <= 0. {
         None
+    } else {
+        Some((wo, wi))
+    }
+}
+
+/// Reflectance at normal incidence for the metallic-roughness model.
+fn pbr_f0(base_color: Color, metallic: Num, specular: Num) -> Color {
+    let dielectric_f0 = 0.08 * specular;
+    let dielectric_f0 = Color::new(dielectric_f0, dielectric_f0, dielectric_f0);
+
+    (1. - metallic) * dielectric_f0 + metallic * base_color
+}
+
+/// The probability of sampling the specular lobe instead of the diffuse lobe.
+fn pbr_specular_probability(base_color: Color, metallic: Num, specular: Num) -> Num {
+    let specular_weight = pbr_f0(base_color, metallic, specular).max_component();
+    let diffuse_weight = (1. - metallic) * base_color.max_component();
+    let total = specular_weight + diffuse_weight;
+
+    if total <= 0. {
+        return 1.;
     }
+
+    // Always leave a chance of sampling either lobe, so neither is starved
+    (specular_weight / total).max(0.1).min(0.9)
 }
 
-fn reflectance(cos: f32, ref_idx: f32) -> f32 {
+fn reflectance(cos: Num, ref_idx: Num) -> Num {
     let r0 = (1. - ref_idx) / (1. + ref_idx);
     let r0 = r0 * r0;
     r0 + (1. - r0) * (1. - cos).powi(5)
@@ -0,0 +1,89 @@
+// GGX (Trowbridge-Reitz) microfacet distribution.
+// All directions are in local shading space, where +Z is the surface normal.
+
+use super::{
+    num::{Num, PI},
+    vec3::{Color, Vec3},
+};
+
+/// The smallest alpha used. Lower values cause the distribution to degenerate into a spike.
+const MIN_ALPHA: Num = 1e-3;
+
+/// Converts a perceptual roughness into the GGX alpha.
+pub fn roughness_to_alpha(roughness: Num) -> Num {
+    (roughness * roughness).max(MIN_ALPHA)
+}
+
+/// The normal distribution function for the half vector `h`.
+pub fn d(h: Vec3, alpha: Num) -> Num {
+    let a2 = alpha * alpha;
+    let denom = h.z * h.z * (a2 - 1.) + 1.;
+
+    a2 / (PI * denom * denom)
+}
+
+/// Smith's auxiliary function for the direction `w`.
+fn lambda(w: Vec3, alpha: Num) -> Num {
+    let cos2 = w.z * w.z;
+    if cos2 <= 0. {
+        return 0.;
+    }
+
+    let tan2 = (1. - cos2).max(0.) / cos2;
+    (-1. + (1. + alpha * alpha * tan2).sqrt()) / 2.
+}
+
+/// Smith masking for a single direction.
+pub fn g1(w: Vec3, alpha: Num) -> Num {
+    1. / (1. + lambda(w, alpha))
+}
+
+/// Height-correlated Smith masking-shadowing for both directions.
+pub fn g2(wo: Vec3, wi: Vec3, alpha: Num) -> Num {
+    1. / (1. + lambda(wo, alpha) + lambda(wi, alpha))
+}
+
+/// Schlick's approximation of the Fresnel term.
+pub fn fresnel_schlick(f0: Color, cos_theta: Num) -> Color {
+    let m = (1. - cos_theta).max(0.).min(1.).powi(5);
+    f0 + (Color::new(1., 1., 1.) - f0) * m
+}
+
+/// Samples a microfacet normal from the distribution of normals visible from `wo`.
+/// `u1` and `u2` are uniform random numbers in `0.0..1.0`.
+/// Based on "Sampling the GGX Distribution of Visible Normals" (Heitz, 2018).
+pub fn sample_vndf(wo: Vec3, alpha: Num, u1: Num, u2: Num) -> Vec3 {
+    // Stretch the view direction into the hemisphere configuration
+    let vh = Vec3::new(alpha * wo.x, alpha * wo.y, wo.z).unit_vector();
+
+    // Orthonormal basis around the view direction
+    let len_sqrd = vh.x * vh.x + vh.y * vh.y;
+    let t1 = if len_sqrd > 0. {
+        Vec3::new(-vh.y, vh.x, 0.) / len_sqrd.sqrt()
+    } else {
+        Vec3::new(1., 0., 0.)
+    };
+    let t2 = vh.cross(t1);
+
+    // Sample the projected area
+    let r = u1.sqrt();
+    let phi = 2. * PI * u2;
+    let p1 = r * phi.cos();
+    let p2 = r * phi.sin();
+    let s = 0.5 * (1. + vh.z);
+    let p2 = (1. - s) * (1. - p1 * p1).max(0.).sqrt() + s * p2;
+
+    // Reproject onto the hemisphere, then unstretch
+    let nh = p1 * t1 + p2 * t2 + (1. - p1 * p1 - p2 * p2).max(0.).sqrt() * vh;
+    Vec3::new(alpha * nh.x, alpha * nh.y, nh.z.max(0.)).unit_vector()
+}
+
+/// The probability density of `sample_vndf` producing the reflected direction for the half vector `h`,
+/// with respect to the solid angle of the reflected direction.
+pub fn reflection_pdf(wo: Vec3, h: Vec3, alpha: Num) -> Num {
+    if wo.z <= 0. {
+        return 0.;
+    }
+
+    g1(wo, alpha) * d(h, alpha) / (4. * wo.z)
+}
@@ -1,110 +1,324 @@
 use crate::renderer::{Command, Size};
+use core_img::Rgba8Image;
 use core_time::Timer;
 
-use hittable::{Hittable, World};
+use conductor::Conductor;
+use hittable::Hittable;
+use integrator::ray_color;
+use ior::Ior;
 use material::Material;
 use std::sync::mpsc::{Receiver, Sender};
 
+mod adaptive;
 mod camera;
+mod conductor;
+mod debug;
 mod hittable;
+mod integrator;
+mod interlace;
+mod ior;
 mod material;
+mod microfacet;
+mod num;
+mod onb;
+mod pick;
 mod ray;
 mod rng;
+mod scene_loader;
+mod simd;
+mod spectral;
+mod temporal;
+mod upscale;
 mod vec3;
 
-use ray::Ray;
+use num::{Num, INFINITY, PI};
 use vec3::{Color, Point3, Vec3};
 
-use self::camera::Camera;
-
-const INFINITY: f32 = std::f32::INFINITY;
-fn deg_to_rads(degrees: f32) -> f32 {
-    degrees * std::f32::consts::PI / 180.
+pub use self::adaptive::AdaptiveSampling;
+use self::adaptive::PixelEstimate;
+pub use self::camera::Projection;
+use self::camera::{Camera, Focus};
+pub use self::debug::DebugMode;
+pub use self::hittable::{ObjectHandle, World};
+pub use self::integrator::{BounceLimits, IntegratorMode};
+pub use self::interlace::Interlace;
+pub use self::pick::Pick;
+pub use self::scene_loader::{build_world, Materials, MATERIALS_PATH};
+use self::temporal::History;
+pub use self::temporal::TemporalSettings;
+use self::upscale::Guide;
+
+fn deg_to_rads(degrees: Num) -> Num {
+    degrees * PI / 180.
 }
 
-const MAX_DRAW: f32 = INFINITY;
-const MIN_DRAW: f32 = 0.001;
+const MAX_DRAW: Num = INFINITY;
+const MIN_DRAW: Num = 0.;
+
+/// How far away the sky is treated as being when reprojecting it.
+const SKY_DISTANCE: Num = 1e4;
+
+/// Generates the built in scene, used when the scene file has no objects. Most of its spheres are placed randomly.
+pub fn generate_world() -> World {
+    perf!("raytracer - world gen");
+
+    use hittable::{
+        capsule::*, cone::*, csg::*, cuboid::*, cylinder::*, paraboloid::*, sdf::*, sphere::*,
+        torus::*, *,
+    };
+    let mut world = World::new();
+
+    let material_ground = Material::Lambertian {
+        albedo: Color::new(0.8, 0.8, 0.0),
+    };
+
+    let s = Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, material_ground);
+    world.add_sphere(s);
+
+    for i in 0..100 {
+        let r = rng::random();
+
+        let material_center = Material::Lambertian {
+            albedo: (rng::random(), rng::random(), rng::random()).into(),
+        };
+        let material_left = if rng::random() < 0.5 {
+            let iors = [Ior::bk7(), Ior::diamond(), Ior::Cauchy { a: 1.5, b: 0.02 }];
+            Material::Dielectric {
+                ior: iors[(rng::random() * iors.len() as Num) as usize % iors.len()],
+                absorption: Color::default(),
+            }
+        } else {
+            let tint = Color::new(rng::random(), rng::random(), rng::random());
+            Material::tinted_dielectric(Ior::Constant(1.5), tint, 1.)
+        };
+        let material_right = Material::Metal {
+            albedo: Color::new(rng::random(), rng::random(), rng::random()),
+            fuzz: 1.,
+        };
+        let material_pbr = Material::Pbr {
+            base_color: Color::new(rng::random(), rng::random(), rng::random()),
+            metallic: 1.,
+            roughness: rng::random(),
+            specular: 0.5,
+        };
+        let presets = [
+            Conductor::Gold,
+            Conductor::Copper,
+            Conductor::Silver,
+            Conductor::Aluminium,
+        ];
+        let preset = presets[(rng::random() * presets.len() as Num) as usize % presets.len()];
+        let material_conductor = Material::conductor(preset, rng::random_range(0., 0.5));
+
+        let radius = rng::random_range(0.1, 1.);
+        let x = rng::random_range(-10., 10.);
+        let y = rng::random_range(0., 1.);
+        let z = rng::random_range(-10., 10.);
+
+        if r < 0.20 {
+            let s = Sphere::new(Point3::new(x, y, z), radius, material_left);
+            world.add_sphere(s);
+        } else if r > 0.2 && r < 0.6 {
+            let s = Sphere::new(Point3::new(x, y, z), radius, material_center);
+            world.add_sphere(s);
+        } else if r < 0.8 {
+            let s = Sphere::new(Point3::new(x, y, z), radius, material_right);
+            world.add_sphere(s);
+        } else if r < 0.9 {
+            let s = Sphere::new(Point3::new(x, y, z), radius, material_pbr);
+            world.add_sphere(s);
+        } else {
+            let s = Sphere::new(Point3::new(x, y, z), radius, material_conductor);
+            world.add_sphere(s);
+        }
+    }
+
+    // A procedural shape: a twisted pillar melted into a ring
+    let pillar = Sdf::cuboid(Vec3::new(0.2, 0.5, 0.2)).twist(2.);
+    let ring = Sdf::torus(0.45, 0.08).translate(Vec3::new(0., -0.35, 0.));
+    let center = Point3::new(0., 0., 0.);
+    let shape = SdfShape::new(
+        pillar.smooth_union(ring, 0.15).translate(center),
+        Some((center, 1.)),
+        Material::conductor(Conductor::Copper, 0.2),
+    );
+    world.add(Box::new(shape));
+
+    // A dome with a groove cut across it, on a bed of beads
+    let dome = Sdf::sphere(0.45).smooth_intersect(Sdf::plane(-Vec3::unit_y(), 0.), 0.05);
+    let groove = Sdf::capsule(
+        Point3::new(-0.5, 0.45, 0.),
+        Point3::new(0.5, 0.45, 0.),
+        0.12,
+    );
+    let beads = Sdf::sphere(0.05)
+        .repeat(Vec3::new(0.2, 0., 0.2))
+        .smooth_intersect(Sdf::cuboid(Vec3::new(0.7, 0.1, 0.7)), 0.)
+        .translate(Vec3::new(0., 0.05, 0.));
+    let center = Point3::new(-0.9, -0.5, 0.9);
+    let shape = SdfShape::new(
+        dome.smooth_subtract(groove, 0.05)
+            .smooth_union(beads, 0.02)
+            .translate(center),
+        Some((center, 1.)),
+        Material::Pbr {
+            base_color: Color::new(0.2, 0.5, 0.8),
+            metallic: 0.,
+            roughness: 0.3,
+            specular: 0.5,
+        },
+    );
+    world.add(Box::new(shape));
+
+    // A sphere with a corner cut out of it, showing the box's material where it's cut
+    let center = Point3::new(0.9, 0., 0.9);
+    let ball = Sphere::new(
+        center,
+        0.5,
+        Material::Pbr {
+            base_color: Color::new(0.8, 0.1, 0.1),
+            metallic: 0.,
+            roughness: 0.4,
+            specular: 0.5,
+        },
+    );
+    let corner = Cuboid::new(
+        center,
+        center + Vec3::new(0.6, 0.6, 0.6),
+        Material::conductor(Conductor::Gold, 0.1),
+    );
+    world.add(Box::new(Csg::new(
+        CsgOp::Difference,
+        Box::new(ball),
+        Box::new(corner),
+    )));
+
+    // A lens from the overlap of two spheres, standing on a block
+    let center = Point3::new(-1.5, -0.05, -0.5);
+    let offset = Vec3::new(0.3, 0., 0.);
+    let silver = Material::conductor(Conductor::Silver, 0.05);
+    let lens = Csg::new(
+        CsgOp::Intersection,
+        Box::new(Sphere::new(center - offset, 0.45, silver)),
+        Box::new(Sphere::new(center + offset, 0.45, silver)),
+    );
+    let stand = Cuboid::new(
+        Point3::new(center.x - 0.1, -0.5, center.z - 0.15),
+        Point3::new(center.x + 0.1, center.y - 0.25, center.z + 0.15),
+        Material::Lambertian {
+            albedo: Color::new(0.3, 0.3, 0.3),
+        },
+    );
+    world.add(Box::new(Csg::new(
+        CsgOp::Union,
+        Box::new(lens),
+        Box::new(stand),
+    )));
+
+    // A row of analytic shapes standing on the ground
+    let up = Vec3::unit_y();
+    let base = |x: Num| Point3::new(x, -0.5, -1.6);
+    let cylinder = Cylinder::new(
+        Frame::new(base(-1.6), up),
+        0.25,
+        0.6,
+        true,
+        Material::Lambertian {
+            albedo: Color::new(0.1, 0.2, 0.7),
+        },
+    );
+    let cone = Cone::new(
+        Frame::new(base(-0.8), up),
+        0.3,
+        0.7,
+        true,
+        Material::Pbr {
+            base_color: Color::new(0.9, 0.5, 0.1),
+            metallic: 0.,
+            roughness: 0.3,
+            specular: 0.5,
+        },
+    );
+    let torus = Torus::new(
+        Frame::new(Point3::new(0., -0.1, -1.6), Vec3::new(0., 1., 1.)),
+        0.3,
+        0.1,
+        Material::conductor(Conductor::Silver, 0.05),
+    );
+    let capsule = Capsule::new(
+        Point3::new(0.8, -0.3, -1.6),
+        Point3::new(0.8, 0.2, -1.6),
+        0.2,
+        Material::Pbr {
+            base_color: Color::new(0.1, 0.6, 0.2),
+            metallic: 0.,
+            roughness: 0.6,
+            specular: 0.5,
+        },
+    );
+    let bowl = Paraboloid::new(
+        Frame::new(base(1.6), up),
+        0.3,
+        0.4,
+        false,
+        Material::conductor(Conductor::Copper, 0.3),
+    );
+    world.add(Box::new(cylinder));
+    world.add(Box::new(cone));
+    world.add(Box::new(torus));
+    world.add(Box::new(capsule));
+    world.add(Box::new(bowl));
+
+    world
+}
 
 pub fn build(
     render_size: Size,
     aa_samples: u32,
+    adaptive_sampling: Option<AdaptiveSampling>,
+    interlace: Interlace,
+    temporal: Option<TemporalSettings>,
+    upscale_factor: u32,
     post_process_aa: bool,
     primary_ray_strength: u32,
-    debug_normals: bool,
+    bounce_limits: BounceLimits,
+    integrator_mode: IntegratorMode,
+    debug_mode: DebugMode,
+    projection: Projection,
 ) -> Raytracer {
-    let max_bounces = 50;
-
     // Camera
-    let aspect_ratio = render_size.width as f32 / render_size.height as f32;
+    let aspect_ratio = render_size.width as Num / render_size.height as Num;
     let v_fov_deg = 90.;
-    let mut camera = camera::Camera::new(aspect_ratio, v_fov_deg);
+    let mut camera = camera::Camera::new(aspect_ratio, v_fov_deg, projection);
     camera.look_at((-2., 2., 1.).into(), (0., 0., -1.).into(), Vec3::unit_y());
 
     let eye = camera.eye();
     let target = camera.target();
     let up = camera.up();
 
-    // Build out scene
-    // Build out scene
-    // TODO: this should not be here
-    let world = {
-        perf!("raytracer - world gen");
-
-        use hittable::{sphere::*, *};
-        let mut world = World::new();
-
-        let material_ground = Material::Lambertian {
-            albedo: Color::new(0.8, 0.8, 0.0),
-        };
-
-        let s = Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, material_ground);
-        world.add(Box::new(s));
-
-        for i in 0..100 {
-            let r = rng::random();
-
-            let material_center = Material::Lambertian {
-                albedo: (rng::random(), rng::random(), rng::random()).into(),
-            };
-            let material_left = Material::Dielectric { ior: 1.5 };
-            let material_right = Material::Metal {
-                albedo: Color::new(rng::random(), rng::random(), rng::random()),
-                fuzz: 1.,
-            };
-
-            let radius = rng::random_range(0.1, 1.);
-            let x = rng::random_range(-10., 10.);
-            let y = rng::random_range(0., 1.);
-            let z = rng::random_range(-10., 10.);
-
-            if r < 0.20 {
-                let s = Sphere::new(Point3::new(x, y, z), radius, material_left);
-                world.add(Box::new(s));
-            } else if r > 0.2 && r < 0.6 {
-                let s = Sphere::new(Point3::new(x, y, z), radius, material_center);
-                world.add(Box::new(s));
-            } else {
-                let s = Sphere::new(Point3::new(x, y, z), radius, material_right);
-                world.add(Box::new(s));
-            }
-        }
-
-        world
-    };
+    let world = generate_world();
 
     let mut tracer = Raytracer {
         v_fov_deg,
         world,
         aspect_ratio,
         aa_samples,
-        max_bounces,
-        debug_normals,
+        adaptive_sampling,
+        interlace,
+        temporal,
+        upscale_factor,
+        bounce_limits,
+        integrator_mode,
+        debug_mode,
         post_process_aa,
         primary_ray_strength,
         camera,
         render_size,
 
         render_commands: vec![],
+        frame: 0,
+        history_valid: false,
+        history: History::new(render_size.width, render_size.height),
 
         eye,
         target,
@@ -117,21 +331,31 @@ pub fn build(
 }
 pub struct Raytracer {
     // image specs
-    aspect_ratio: f32,
+    aspect_ratio: Num,
     render_size: Size,
-    v_fov_deg: f32,
+    v_fov_deg: Num,
 
     // world
     world: World,
 
     /// sampling
     aa_samples: u32,
-    max_bounces: u32,
+    adaptive_sampling: Option<AdaptiveSampling>,
+    interlace: Interlace,
+    temporal: Option<TemporalSettings>,
+    /// Only one pixel in each `upscale_factor` x `upscale_factor` block is path traced
+    upscale_factor: u32,
+    bounce_limits: BounceLimits,
+    integrator_mode: IntegratorMode,
     post_process_aa: bool,
     primary_ray_strength: u32,
 
     // rendering
     render_commands: Vec<Command>,
+    frame: u64,
+    /// Whether `render_commands` holds a previous frame that untraced pixels can be filled from
+    history_valid: bool,
+    history: History,
 
     // Camera
     camera: Camera,
@@ -140,21 +364,25 @@ pub struct Raytracer {
     up: Vec3,
 
     // Debugging
-    debug_normals: bool,
+    debug_mode: DebugMode,
 }
 
 impl Raytracer {
     pub fn resize(&mut self, render_size: Size) {
-        let camera = Camera::new(
-            render_size.width as f32 / render_size.height as f32,
+        let mut camera = Camera::new(
+            render_size.width as Num / render_size.height as Num,
             self.v_fov_deg,
+            self.camera.projection(),
         );
+        camera.set_focus(self.camera.focus());
         let render_commands =
             vec![Command::default(); render_size.width as usize * render_size.height as usize];
 
         self.camera = camera;
         self.render_commands = render_commands;
         self.render_size = render_size;
+        self.history = History::new(render_size.width, render_size.height);
+        self.history_valid = false;
     }
 
     pub fn look_at(
@@ -163,10 +391,10 @@ impl Raytracer {
         target: (f32, f32, f32),
         up: Option<(f32, f32, f32)>,
     ) {
-        let eye: Point3 = eye.into();
-        let target: Point3 = target.into();
-        let up: Vec3 = match up {
-            Some(up) => up.into(),
+        let eye = Point3::from_f32(eye);
+        let target = Point3::from_f32(target);
+        let up = match up {
+            Some(up) => Vec3::from_f32(up),
             None => Vec3::unit_y(),
         };
 
@@ -175,6 +403,92 @@ impl Raytracer {
         self.up = up;
     }
 
+    /// Changes the vertical field of view.
+    pub fn set_v_fov(&mut self, v_fov_deg: f32) {
+        self.v_fov_deg = v_fov_deg as Num;
+        self.camera.set_v_fov(self.v_fov_deg);
+    }
+
+    /// Sets the depth of field. Everything is in focus with an `aperture` of `0`.
+    pub fn set_focus(&mut self, focus_distance: f32, aperture: f32) {
+        let focus = if aperture > 0. {
+            Some(Focus {
+                distance: focus_distance as Num,
+                aperture: aperture as Num,
+            })
+        } else {
+            None
+        };
+
+        self.camera.set_focus(focus);
+    }
+
+    pub fn integrator_mode(&self) -> IntegratorMode {
+        self.integrator_mode
+    }
+
+    /// Switches between RGB and spectral rendering.
+    pub fn set_integrator_mode(&mut self, integrator_mode: IntegratorMode) {
+        self.integrator_mode = integrator_mode;
+        self.invalidate_history();
+    }
+
+    /// Switches the debug visualization that is rendered.
+    pub fn set_debug_mode(&mut self, debug_mode: DebugMode) {
+        self.debug_mode = debug_mode;
+        self.invalidate_history();
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.camera.projection()
+    }
+
+    /// Switches how the camera maps pixels onto rays.
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.camera.set_projection(projection);
+        self.invalidate_history();
+    }
+
+    pub fn interlace(&self) -> Interlace {
+        self.interlace
+    }
+
+    /// Switches which pixels are traced each frame.
+    pub fn set_interlace(&mut self, interlace: Interlace) {
+        self.interlace = interlace;
+        self.invalidate_history();
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// The world, for adding and removing objects, or moving and replacing them through their handles.
+    /// Makes the next frame trace every pixel, as the history may no longer match the scene.
+    pub fn world_mut(&mut self) -> &mut World {
+        self.invalidate_history();
+        &mut self.world
+    }
+
+    /// Finds the object at the screen position, as of the last render.
+    /// `x` and `y` are each in `0.0..1.0`, from the top left.
+    pub fn pick(&self, x: f32, y: f32) -> Option<Pick> {
+        pick::pick(&self.camera, &self.world, x, y)
+    }
+
+    /// Raytraces a frame, returning it as an image with a pixel per render command.
+    pub fn render_image(&mut self) -> Rgba8Image {
+        let (sender, receiver): (Sender<Command>, Receiver<Command>) = std::sync::mpsc::channel();
+        self.execute_render(sender);
+
+        let mut img = Rgba8Image::new(self.render_size.width, self.render_size.height);
+        for cmd in receiver.try_iter() {
+            img.put_pixel(cmd.x, cmd.y, cmd.color.r, cmd.color.g, cmd.color.b, u8::MAX);
+        }
+
+        img
+    }
+
     /// Raytraces the scene, sending commands to the renderer.
     /// `render_size` is the number of rays to send
     /// `render_queue` is the mechanism to communicate with the renderer
@@ -201,39 +515,24 @@ impl Raytracer {
 
             let timer = Timer::new();
 
+            let estimates = self.trace_estimates();
+            let accumulated = self.accumulate(&estimates);
+
             for x in 0..self.render_size.width {
                 for y in 0..self.render_size.height {
                     // Get the color from the scene
-                    let color = {
-                        // Get the initial color for the center of the ray
-                        let (u, v) = make_uv(
-                            x,
-                            y,
-                            self.render_size.width,
-                            self.render_size.height,
-                            0.,
-                            0.,
-                        );
-                        let r = self.camera.get_ray(u, v);
-                        let mut color =
-                            ray_color(&r, &self.world, self.max_bounces, self.debug_normals);
-
-                        // Do AA
-                        for _sample in 0..self.aa_samples {
-                            let (u, v) = make_uv(
-                                x,
-                                y,
-                                self.render_size.width,
-                                self.render_size.height,
-                                rng::random(),
-                                rng::random(),
-                            );
-                            let r = self.camera.get_ray(u, v);
-                            color +=
-                                ray_color(&r, &self.world, self.max_bounces, self.debug_normals);
-                        }
-
-                        color
+                    let i = core_conversions::index_2d_to_1d(
+                        x as usize,
+                        y as usize,
+                        self.render_size.width as usize,
+                    );
+                    if !self.is_traced(x, y) {
+                        continue;
+                    }
+
+                    let color = match &accumulated {
+                        Some(colors) => to_color(colors[i], 1),
+                        None => self.resolve_pixel(&estimates[i]),
                     };
 
                     // Send it off
@@ -241,7 +540,7 @@ impl Raytracer {
                         .send(Command {
                             c: '感',
                             //c: '█',
-                            color: to_color(color, self.aa_samples),
+                            color,
                             x,
                             y,
                         })
@@ -324,76 +623,262 @@ impl Raytracer {
         for cmd in &self.render_commands {
             render_queue.send(*cmd).unwrap();
         }
+
+        self.frame += 1;
+        self.history_valid = true;
     }
 }
 
-/// Attempt to add two values to the vec if they're within the bounds
-fn try_add_from_grid(val: usize, max: u32, v: &mut Vec<usize>) {
-    if val > 0 {
-        v.push(val - 1);
+impl Raytracer {
+    /// Makes the next frame trace every pixel, without any history.
+    fn invalidate_history(&mut self) {
+        self.history_valid = false;
+        self.history.reset();
     }
-    if val < max as usize - 1 {
-        v.push(val + 1);
+
+    /// Blends this frame's colors with the reprojected history.
+    /// Returns the linear colors in row major order, or `None` if temporal accumulation is off.
+    /// Debug views are never accumulated.
+    fn accumulate(&mut self, estimates: &[PixelEstimate]) -> Option<Vec<Color>> {
+        let settings = self.temporal?;
+        if self.debug_mode != DebugMode::Off {
+            return None;
+        }
+
+        let colors: Vec<Color> = estimates
+            .iter()
+            .map(|e| e.sum / e.samples().max(1) as Num)
+            .collect();
+        let points = self.primary_points();
+
+        let width = self.render_size.width;
+        let traced: Vec<bool> = (0..width * self.render_size.height)
+            .map(|i| self.is_traced(i % width, i / width))
+            .collect();
+
+        Some(
+            self.history
+                .resolve(&settings, &self.camera, &colors, &points, &traced),
+        )
     }
-}
 
-fn make_uv(x: u32, y: u32, max_x: u32, max_y: u32, u_offset: f32, v_offset: f32) -> (f32, f32) {
-    let u = x as f32 / (max_x - 1) as f32;
+    /// The world position of the primary hit through the center of each traced pixel, in row major order.
+    /// Misses are placed far away along the ray, so the sky can be reprojected too.
+    fn primary_points(&self) -> Vec<Point3> {
+        let width = self.render_size.width;
+        let height = self.render_size.height;
+
+        let mut points = vec![Point3::default(); width as usize * height as usize];
+        for y in 0..height {
+            for x in 0..width {
+                if !self.is_traced(x, y) {
+                    continue;
+                }
 
-    // Flip y, otherwise normals are borked
-    let v = ((max_y - 1) - y) as f32 / (max_y - 1) as f32;
+                let (u, v) = make_uv(x, y, width, height, 0.5, 0.5);
+                let r = self.camera.get_ray(u, v);
+                let i = core_conversions::index_2d_to_1d(x as usize, y as usize, width as usize);
 
-    (u, v)
-}
+                points[i] = match self.world.hit(&r, MIN_DRAW, MAX_DRAW) {
+                    Some(rec) => rec.point,
+                    None => r.origin() + SKY_DISTANCE * r.direction().unit_vector(),
+                };
+            }
+        }
+
+        points
+    }
 
-fn ray_color(ray: &Ray, world: &World, bounces: u32, debug_normals: bool) -> Color {
-    if bounces == 0 {
-        return Color::default();
+    /// Whether the pixel is traced this frame, rather than kept from the previous one.
+    /// When upscaling, whole blocks are interlaced together.
+    fn is_traced(&self, x: u32, y: u32) -> bool {
+        let factor = self.upscale_factor.max(1);
+        !self.history_valid || self.interlace.is_traced(x / factor, y / factor, self.frame)
     }
 
-    // Scene stuff
-    {
-        if let Some(rec) = world.hit(ray, MIN_DRAW, MAX_DRAW) {
-            // Bounce a ray to simulate light
-            let target = rec.point + Vec3::random_in_hemisphere(rec.normal);
+    /// Whether the pixel is path traced, rather than upscaled from its neighbors.
+    fn is_sample(&self, x: u32, y: u32) -> bool {
+        let factor = self.upscale_factor.max(1);
+        upscale::is_sample(
+            x,
+            y,
+            self.render_size.width,
+            self.render_size.height,
+            factor,
+        )
+    }
 
-            // Normal test
-            if debug_normals {
-                return 0.5 * (rec.normal + Color::new(1., 1., 1.));
+    /// Traces the pixels for this frame, returning the estimates in row major order.
+    /// Pixels that aren't traced this frame have empty estimates.
+    /// With adaptive sampling, the frame budget is spread across the noisiest pixels; otherwise
+    /// every pixel gets the same number of samples. When upscaling, only the block samples are traced
+    /// and the rest of each block is filled from them.
+    fn trace_estimates(&self) -> Vec<PixelEstimate> {
+        let width = self.render_size.width;
+        let height = self.render_size.height;
+
+        let initial_samples = match self.adaptive_sampling {
+            Some(settings) => settings.initial_samples(),
+            None => self.aa_samples + 1,
+        };
+
+        let mut traced_pixels = 0;
+        let mut estimates = vec![PixelEstimate::default(); width as usize * height as usize];
+        for y in 0..height {
+            for x in 0..width {
+                if !self.is_traced(x, y) || !self.is_sample(x, y) {
+                    continue;
+                }
+
+                traced_pixels += 1;
+                let i = core_conversions::index_2d_to_1d(x as usize, y as usize, width as usize);
+                for _ in 0..initial_samples {
+                    self.sample_pixel(x, y, &mut estimates[i]);
+                }
             }
+        }
+
+        if let Some(settings) = self.adaptive_sampling {
+            // Only spend the share of the budget for the pixels that were traced
+            let budget =
+                settings.frame_budget as u64 * traced_pixels / estimates.len().max(1) as u64;
 
-            if let Some((scattered, attenuation)) = rec.material.scatter(ray, &rec) {
-                return attenuation * ray_color(&scattered, world, bounces - 1, debug_normals);
-            } else {
-                return Color::default();
+            adaptive::refine(&mut estimates, &settings, budget as u32, |i, estimate| {
+                let x = (i % width as usize) as u32;
+                let y = (i / width as usize) as u32;
+                self.sample_pixel(x, y, estimate);
+            });
+        }
+
+        if self.upscale_factor > 1 {
+            let guides = self.guides();
+            upscale::upsample(
+                width,
+                height,
+                self.upscale_factor,
+                &guides,
+                &mut estimates,
+                |x, y| self.is_traced(x, y),
+            );
+        }
+
+        estimates
+    }
+
+    /// The primary hit through the center of each traced pixel, in row major order.
+    fn guides(&self) -> Vec<Guide> {
+        let width = self.render_size.width;
+        let height = self.render_size.height;
+
+        let mut guides = vec![Guide::default(); width as usize * height as usize];
+        for y in 0..height {
+            for x in 0..width {
+                if !self.is_traced(x, y) {
+                    continue;
+                }
+
+                let (u, v) = make_uv(x, y, width, height, 0.5, 0.5);
+                let r = self.camera.get_ray(u, v);
+                let i = core_conversions::index_2d_to_1d(x as usize, y as usize, width as usize);
+
+                guides[i] = Guide::new(&r, &self.world);
             }
+        }
+
+        guides
+    }
+
+    /// Takes a single sample for the given pixel, adding it to the estimate.
+    fn sample_pixel(&self, x: u32, y: u32, estimate: &mut PixelEstimate) {
+        // The first sample is always the center of the pixel
+        let (u_offset, v_offset) = if estimate.samples() == 0 {
+            (0.5, 0.5)
+        } else {
+            (rng::random(), rng::random())
+        };
+
+        let (u, v) = make_uv(
+            x,
+            y,
+            self.render_size.width,
+            self.render_size.height,
+            u_offset,
+            v_offset,
+        );
+        let r = self.camera.get_lens_ray(u, v);
+
+        estimate.stats.samples += 1;
+        let color = if self.debug_mode.is_surface_view() {
+            debug::surface_color(self.debug_mode, &r, &self.world)
+        } else {
+            ray_color(
+                &r,
+                &self.world,
+                &self.bounce_limits,
+                self.integrator_mode,
+                &mut estimate.stats,
+            )
+        };
+
+        estimate.add(color);
+    }
 
-            // Old
-            return 0.5
-                * ray_color(
-                    &Ray::new(rec.point, target - rec.point),
-                    world,
-                    bounces - 1,
-                    debug_normals,
-                );
+    /// Converts the pixel's estimate into its final color.
+    fn resolve_pixel(&self, estimate: &PixelEstimate) -> core_renderer::Color {
+        if self.debug_mode.is_heatmap() {
+            let max_samples = match self.adaptive_sampling {
+                Some(settings) => settings.max_samples.max(settings.initial_samples()),
+                None => self.aa_samples + 1,
+            };
+            let max_tests = self.world.max_tests() * self.bounce_limits.total;
+            let heat = debug::stats_color(
+                self.debug_mode,
+                &estimate.stats,
+                max_tests,
+                self.bounce_limits.total,
+                max_samples,
+            );
+
+            return to_rgba(heat);
         }
+
+        to_color(estimate.sum, estimate.samples())
     }
+}
 
-    // Sky color
-    let unit_dir = ray.direction().unit_vector();
-    let t = 0.5 * (unit_dir.y + 1.);
-    (1. - t) * Color::new(1., 1., 1.) + t * Color::new(0.5, 0.7, 1.)
+/// Attempt to add two values to the vec if they're within the bounds
+fn try_add_from_grid(val: usize, max: u32, v: &mut Vec<usize>) {
+    if val > 0 {
+        v.push(val - 1);
+    }
+    if val < max as usize - 1 {
+        v.push(val + 1);
+    }
 }
 
-fn to_color(v: Vec3, aa_samples: u32) -> core_renderer::Color {
-    let aa_samples = aa_samples as f32 + 1.; // Always add 1 in the case that there's 0 samples
+/// `u_offset` and `v_offset` are the position within the pixel, where `0.5` is the center.
+fn make_uv(x: u32, y: u32, max_x: u32, max_y: u32, u_offset: Num, v_offset: Num) -> (Num, Num) {
+    let u = (x as Num + u_offset - 0.5) / (max_x - 1) as Num;
+
+    // Flip y, otherwise normals are borked
+    let v = (((max_y - 1) - y) as Num + v_offset - 0.5) / (max_y - 1) as Num;
 
-    const C: f32 = 255.;
-    let scale = 1. / aa_samples;
+    (u, v)
+}
+
+fn to_color(v: Vec3, samples: u32) -> core_renderer::Color {
+    // Divide color by number of samples and gamma correct for gamma = 2.0
+    let scale = 1. / samples.max(1) as Num;
     let v = v * scale;
     let v = Vec3::new(v.x.sqrt(), v.y.sqrt(), v.z.sqrt());
+
+    to_rgba(v)
+}
+
+/// Converts a `0.0..1.0` color to an 8bit color, without any gamma correction.
+fn to_rgba(v: Vec3) -> core_renderer::Color {
+    const C: Num = 255.;
     let v = v * C;
-    // Divide color by number of samples and gamma correct for gamma = 2.0
 
     let r: u8 = clamp(v.x, 0., C) as u8;
     let g: u8 = clamp(v.y, 0., C) as u8;
@@ -403,7 +888,7 @@ fn to_color(v: Vec3, aa_samples: u32) -> core_renderer::Color {
     (r, g, b, a).into()
 }
 
-fn clamp(v: f32, min: f32, max: f32) -> f32 {
+fn clamp(v: Num, min: Num, max: Num) -> Num {
     if v < min {
         min
     } else if v > max {
@@ -0,0 +1,27 @@
+// Floating point precision used by the raytracer. Enable the `f64` feature for double precision.
+
+#[cfg(not(feature = "f64"))]
+pub type Num = f32;
+#[cfg(not(feature = "f64"))]
+pub use std::f32::{consts::PI, EPSILON, INFINITY};
+
+#[cfg(feature = "f64")]
+pub type Num = f64;
+#[cfg(feature = "f64")]
+pub use std::f64::{consts::PI, EPSILON, INFINITY};
+
+/// Moves the value the given number of representable floats away from zero. Negative values move towards zero.
+#[cfg(not(feature = "f64"))]
+pub fn offset_ulps(v: Num, ulps: i32) -> Num {
+    let bits = v.to_bits() as i32;
+    let offset = if v < 0. { -ulps } else { ulps };
+    Num::from_bits(bits.wrapping_add(offset) as u32)
+}
+
+/// Moves the value the given number of representable floats away from zero. Negative values move towards zero.
+#[cfg(feature = "f64")]
+pub fn offset_ulps(v: Num, ulps: i32) -> Num {
+    let bits = v.to_bits() as i64;
+    let offset = if v < 0. { -ulps as i64 } else { ulps as i64 };
+    Num::from_bits(bits.wrapping_add(offset) as u64)
+}
@@ -0,0 +1,36 @@
+// Object picking. Casts a ray through a point on the screen and reports what it hits, for inspecting the scene.
+
+use super::{
+    camera::Camera,
+    hittable::{ObjectHandle, World},
+    material::Material,
+    num::Num,
+    MAX_DRAW, MIN_DRAW,
+};
+
+/// What was hit by a pick.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pick {
+    pub object: ObjectHandle,
+    pub point: (f32, f32, f32),
+    /// Unit surface normal, facing the camera
+    pub normal: (f32, f32, f32),
+    /// Distance from the camera to the hit
+    pub distance: f32,
+    pub material: Material,
+}
+
+/// Picks the closest object through the screen position. `x` and `y` are each in `0.0..1.0`, from the top left.
+pub fn pick(camera: &Camera, world: &World, x: f32, y: f32) -> Option<Pick> {
+    // The camera's `t` goes up the screen
+    let ray = camera.get_ray(x as Num, 1. - y as Num);
+    let (object, rec) = world.hit_object(&ray, MIN_DRAW, MAX_DRAW)?;
+
+    Some(Pick {
+        object,
+        point: rec.point.to_f32(),
+        normal: rec.normal.to_f32(),
+        distance: (rec.t * ray.direction().len()) as f32,
+        material: rec.material,
+    })
+}
@@ -1,14 +1,55 @@
-use crate::core_raytracer::vec3::{Point3, Vec3};
+use crate::core_raytracer::{
+    num::{offset_ulps, Num},
+    vec3::{Point3, Vec3},
+};
 
-#[derive(Default)]
+/// Points closer to the origin than this are offset by a fixed amount, as floats are too dense there.
+const OFFSET_ORIGIN: Num = 1. / 32.;
+/// Fixed offset scale used near the origin.
+const OFFSET_FLOAT_SCALE: Num = 1. / 65536.;
+/// Offset scale, in representable floats, used everywhere else.
+const OFFSET_INT_SCALE: Num = 256.;
+
+/// Moves a point on a surface off of it along the normal, so rays spawned from it don't hit the same surface.
+/// The offset scales with the magnitude of the point, so it works regardless of how far the point is from the origin.
+/// Based on "A Fast and Robust Method for Avoiding Self-Intersection" (Wächter and Binder, 2019).
+pub fn offset_origin(point: Point3, normal: Vec3) -> Point3 {
+    let offset = |p: Num, n: Num| {
+        if p.abs() < OFFSET_ORIGIN {
+            p + OFFSET_FLOAT_SCALE * n
+        } else {
+            offset_ulps(p, (OFFSET_INT_SCALE * n) as i32)
+        }
+    };
+
+    Point3::new(
+        offset(point.x, normal.x),
+        offset(point.y, normal.y),
+        offset(point.z, normal.z),
+    )
+}
+
+#[derive(Copy, Clone, Debug, Default)]
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
+    /// The wavelength, in nanometers, the ray carries when rendering spectrally
+    wavelength: Option<Num>,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vec3) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            wavelength: None,
+        }
+    }
+
+    /// Returns the ray, tagged with the given wavelength.
+    pub fn with_wavelength(mut self, wavelength: Num) -> Self {
+        self.wavelength = Some(wavelength);
+        self
     }
 
     pub fn origin(&self) -> Point3 {
@@ -19,7 +60,11 @@ impl Ray {
         self.direction
     }
 
-    pub fn at(&self, t: f32) -> Point3 {
+    pub fn wavelength(&self) -> Option<Num> {
+        self.wavelength
+    }
+
+    pub fn at(&self, t: Num) -> Point3 {
         self.origin + t * self.direction
     }
 }
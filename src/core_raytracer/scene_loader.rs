@@ -0,0 +1,228 @@
+// Builds worlds from the scene and materials files. Each section of the materials file is a named material, with a
+// `type` and the parameters for that type. Parameters that are left out use the defaults below.
+
+use super::{
+    conductor::Conductor,
+    hittable::{heightfield::Heightfield, sphere::Sphere, World},
+    ior::Ior,
+    material::Material,
+    num::Num,
+    vec3::{Color, Point3, Vec3},
+};
+use crate::cfg::{self, CfgError, Field};
+use crate::scene::Scene;
+use core_img::Rgba8Image;
+use std::collections::HashMap;
+
+pub const MATERIALS_PATH: &str = "res/materials.cfg";
+
+/// The materials in a materials file, by name.
+pub struct Materials {
+    by_name: HashMap<String, Material>,
+}
+
+impl Materials {
+    /// Reads and parses the materials file, formatting any error for display.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, CfgError> {
+        let mut by_name = HashMap::new();
+
+        for section in cfg::sections(text)? {
+            let kind = section.get("type").ok_or_else(|| section.missing("type"))?;
+
+            let mut albedo = Color::new(0.5, 0.5, 0.5);
+            let mut fuzz = 0.;
+            let mut ior = Ior::Constant(1.5);
+            let mut tint = None;
+            let mut metallic = 0.;
+            let mut roughness = 0.5;
+            let mut specular = 0.5;
+            let mut metal = Conductor::Gold;
+
+            for field in &section.fields {
+                match field.name {
+                    "type" => {}
+                    "albedo" | "base_color" => albedo = color(field)?,
+                    "fuzz" => fuzz = field.num()? as Num,
+                    "ior" => {
+                        ior = match field.value {
+                            "bk7" => Ior::bk7(),
+                            "diamond" => Ior::diamond(),
+                            _ => Ior::Constant(field.num()? as Num),
+                        }
+                    }
+                    "tint" => tint = Some(color(field)?),
+                    "metallic" => metallic = field.num()? as Num,
+                    "roughness" => roughness = field.num()? as Num,
+                    "specular" => specular = field.num()? as Num,
+                    "metal" => {
+                        metal = match field.value {
+                            "gold" => Conductor::Gold,
+                            "copper" => Conductor::Copper,
+                            "silver" => Conductor::Silver,
+                            "aluminium" => Conductor::Aluminium,
+                            _ => return Err(field.invalid()),
+                        }
+                    }
+                    _ => return Err(field.unknown()),
+                }
+            }
+
+            let material = match kind.value {
+                "lambertian" => Material::Lambertian { albedo },
+                "metal" => Material::Metal { albedo, fuzz },
+                "dielectric" => match tint {
+                    Some(tint) => Material::tinted_dielectric(ior, tint, 1.),
+                    None => Material::Dielectric {
+                        ior,
+                        absorption: Color::default(),
+                    },
+                },
+                "pbr" => Material::Pbr {
+                    base_color: albedo,
+                    metallic,
+                    roughness,
+                    specular,
+                },
+                "conductor" => Material::conductor(metal, roughness),
+                _ => return Err(kind.invalid()),
+            };
+
+            by_name.insert(section.name.to_string(), material);
+        }
+
+        Ok(Self { by_name })
+    }
+
+    /// The named material, for the object on the given line of the scene.
+    fn get(&self, name: &str, line: usize) -> Result<Material, CfgError> {
+        self.by_name
+            .get(name)
+            .copied()
+            .ok_or_else(|| CfgError::UnknownName {
+                line,
+                name: name.to_string(),
+            })
+    }
+}
+
+/// Creates a world with the scene's objects. Fails if an object uses a material that doesn't exist, or an image that
+/// can't be loaded.
+pub fn build_world(scene: &Scene, materials: &Materials) -> Result<World, CfgError> {
+    let mut world = World::new();
+
+    for sphere in &scene.spheres {
+        let material = materials.get(&sphere.material, sphere.line)?;
+        let center = Point3::from_f32(sphere.center);
+        world.add_sphere(Sphere::new(center, sphere.radius as Num, material));
+    }
+
+    for heightfield in &scene.heightfields {
+        let material = materials.get(&heightfield.material, heightfield.line)?;
+        let image =
+            Rgba8Image::open(&heightfield.image).map_err(|reason| CfgError::UnreadableFile {
+                line: heightfield.line,
+                path: heightfield.image.clone(),
+                reason,
+            })?;
+
+        world.add(Box::new(Heightfield::from_image(
+            &image,
+            Point3::from_f32(heightfield.corner),
+            Vec3::from_f32(heightfield.size),
+            material,
+        )));
+    }
+
+    Ok(world)
+}
+
+fn color(field: &Field) -> Result<Color, CfgError> {
+    field.vec3().map(Color::from_f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATERIALS: &str =
+        "[ground]\ntype = lambertian\nalbedo = 0.8, 0.8, 0\n[glass]\ntype = dielectric\n";
+
+    #[test]
+    fn materials_parse() {
+        let materials = Materials::parse(MATERIALS).unwrap();
+
+        assert!(materials.get("ground", 1).is_ok());
+        assert!(materials.get("glass", 1).is_ok());
+        assert_eq!(
+            Some(CfgError::MissingField {
+                line: 1,
+                field: "type".to_string()
+            }),
+            Materials::parse("[ground]\nalbedo = 1, 1, 1\n").err()
+        );
+        assert_eq!(
+            Some(CfgError::InvalidValue {
+                line: 2,
+                field: "type".to_string()
+            }),
+            Materials::parse("[ground]\ntype = plastic\n").err()
+        );
+    }
+
+    #[test]
+    fn build_world_from_scene() {
+        let materials = Materials::parse(MATERIALS).unwrap();
+        let scene = Scene::parse(
+            "[sphere]\ncenter = 0, 0, -1\nradius = 0.5\nmaterial = glass\n\
+             [sphere]\ncenter = 0, -100.5, -1\nradius = 100\nmaterial = ground\n",
+        )
+        .unwrap();
+
+        let world = build_world(&scene, &materials).ok().unwrap();
+        assert_eq!(2, world.len());
+    }
+
+    #[test]
+    fn build_world_unknown_material() {
+        let materials = Materials::parse(MATERIALS).unwrap();
+        let scene = Scene::parse(
+            "[sphere]\ncenter = 0, 0, -1\nradius = 0.5\nmaterial = ground\n\n\
+             [sphere]\ncenter = 0, 0, -1\nradius = 0.5\nmaterial = gold\n",
+        )
+        .unwrap();
+
+        // The error points at the object using the material
+        assert_eq!(
+            Some(CfgError::UnknownName {
+                line: 6,
+                name: "gold".to_string()
+            }),
+            build_world(&scene, &materials).err()
+        );
+    }
+
+    #[test]
+    fn build_world_unreadable_image() {
+        let materials = Materials::parse(MATERIALS).unwrap();
+        let scene = Scene::parse(
+            "[heightfield]\nimage = res/missing.png\ncorner = 0, 0, 0\nsize = 1, 1, 1\nmaterial = ground\n",
+        )
+        .unwrap();
+
+        match build_world(&scene, &materials).err() {
+            Some(CfgError::UnreadableFile { line, path, reason }) => {
+                assert_eq!(1, line);
+                assert_eq!("res/missing.png", path);
+                assert!(!reason.is_empty());
+            }
+            _ => panic!("expected the image to be unreadable"),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+// Vectors of `Num`, for testing a ray against several primitives at once. `NumX4` uses SSE on x86_64, which is
+// always available there, and `NumX8` uses AVX when the build targets it, such as with `-C target-cpu=native`.
+// Everywhere else, including when the `f64` feature is enabled, `NumX4` falls back to plain arrays.
+// `Wide` is the widest vector available and `LANES` is its width.
+// Comparisons return masks, which should only be consumed by `and`, `or`, `select` and `bitmask`.
+
+use super::num::Num;
+
+#[cfg(all(target_arch = "x86_64", not(feature = "f64")))]
+use std::arch::x86_64::*;
+
+#[cfg(all(target_arch = "x86_64", target_feature = "avx", not(feature = "f64")))]
+pub type Wide = NumX8;
+#[cfg(all(target_arch = "x86_64", target_feature = "avx", not(feature = "f64")))]
+pub const LANES: usize = 8;
+
+#[cfg(not(all(target_arch = "x86_64", target_feature = "avx", not(feature = "f64"))))]
+pub type Wide = NumX4;
+#[cfg(not(all(target_arch = "x86_64", target_feature = "avx", not(feature = "f64"))))]
+pub const LANES: usize = 4;
+
+#[cfg(all(target_arch = "x86_64", target_feature = "avx", not(feature = "f64")))]
+#[derive(Copy, Clone, Debug)]
+pub struct NumX8(__m256);
+
+#[cfg(all(
+    target_arch = "x86_64",
+    not(target_feature = "avx"),
+    not(feature = "f64")
+))]
+#[derive(Copy, Clone, Debug)]
+pub struct NumX4(__m128);
+
+#[cfg(any(not(target_arch = "x86_64"), feature = "f64"))]
+#[derive(Copy, Clone, Debug)]
+pub struct NumX4([Num; 4]);
+
+#[cfg(all(target_arch = "x86_64", target_feature = "avx", not(feature = "f64")))]
+impl NumX8 {
+    pub fn splat(v: Num) -> Self {
+        unsafe { Self(_mm256_set1_ps(v)) }
+    }
+
+    /// Loads the first 8 values of the slice.
+    pub fn load(v: &[Num]) -> Self {
+        assert!(v.len() >= 8);
+        unsafe { Self(_mm256_loadu_ps(v.as_ptr())) }
+    }
+
+    pub fn to_array(self) -> [Num; 8] {
+        let mut out = [0.; 8];
+        unsafe { _mm256_storeu_ps(out.as_mut_ptr(), self.0) };
+        out
+    }
+
+    pub fn sqrt(self) -> Self {
+        unsafe { Self(_mm256_sqrt_ps(self.0)) }
+    }
+
+    pub fn max(self, rhs: Self) -> Self {
+        unsafe { Self(_mm256_max_ps(self.0, rhs.0)) }
+    }
+
+    pub fn lt(self, rhs: Self) -> Self {
+        unsafe { Self(_mm256_cmp_ps(self.0, rhs.0, _CMP_LT_OQ)) }
+    }
+
+    pub fn le(self, rhs: Self) -> Self {
+        unsafe { Self(_mm256_cmp_ps(self.0, rhs.0, _CMP_LE_OQ)) }
+    }
+
+    pub fn ge(self, rhs: Self) -> Self {
+        unsafe { Self(_mm256_cmp_ps(self.0, rhs.0, _CMP_GE_OQ)) }
+    }
+
+    pub fn and(self, rhs: Self) -> Self {
+        unsafe { Self(_mm256_and_ps(self.0, rhs.0)) }
+    }
+
+    pub fn or(self, rhs: Self) -> Self {
+        unsafe { Self(_mm256_or_ps(self.0, rhs.0)) }
+    }
+
+    /// Picks lanes from `a` where the mask is set, otherwise from `b`.
+    pub fn select(mask: Self, a: Self, b: Self) -> Self {
+        unsafe { Self(_mm256_blendv_ps(b.0, a.0, mask.0)) }
+    }
+
+    /// Packs the mask into the low bits of an int. Lane 0 is bit 0.
+    pub fn bitmask(self) -> u32 {
+        unsafe { _mm256_movemask_ps(self.0) as u32 }
+    }
+}
+
+#[cfg(all(
+    target_arch = "x86_64",
+    not(target_feature = "avx"),
+    not(feature = "f64")
+))]
+impl NumX4 {
+    pub fn splat(v: Num) -> Self {
+        unsafe { Self(_mm_set1_ps(v)) }
+    }
+
+    /// Loads the first 4 values of the slice.
+    pub fn load(v: &[Num]) -> Self {
+        assert!(v.len() >= 4);
+        unsafe { Self(_mm_loadu_ps(v.as_ptr())) }
+    }
+
+    pub fn to_array(self) -> [Num; 4] {
+        let mut out = [0.; 4];
+        unsafe { _mm_storeu_ps(out.as_mut_ptr(), self.0) };
+        out
+    }
+
+    pub fn sqrt(self) -> Self {
+        unsafe { Self(_mm_sqrt_ps(self.0)) }
+    }
+
+    pub fn max(self, rhs: Self) -> Self {
+        unsafe { Self(_mm_max_ps(self.0, rhs.0)) }
+    }
+
+    pub fn lt(self, rhs: Self) -> Self {
+        unsafe { Self(_mm_cmplt_ps(self.0, rhs.0)) }
+    }
+
+    pub fn le(self, rhs: Self) -> Self {
+        unsafe { Self(_mm_cmple_ps(self.0, rhs.0)) }
+    }
+
+    pub fn ge(self, rhs: Self) -> Self {
+        unsafe { Self(_mm_cmpge_ps(self.0, rhs.0)) }
+    }
+
+    pub fn and(self, rhs: Self) -> Self {
+        unsafe { Self(_mm_and_ps(self.0, rhs.0)) }
+    }
+
+    pub fn or(self, rhs: Self) -> Self {
+        unsafe { Self(_mm_or_ps(self.0, rhs.0)) }
+    }
+
+    /// Picks lanes from `a` where the mask is set, otherwise from `b`.
+    pub fn select(mask: Self, a: Self, b: Self) -> Self {
+        unsafe {
+            Self(_mm_or_ps(
+                _mm_and_ps(mask.0, a.0),
+                _mm_andnot_ps(mask.0, b.0),
+            ))
+        }
+    }
+
+    /// Packs the mask into the low bits of an int. Lane 0 is bit 0.
+    pub fn bitmask(self) -> u32 {
+        unsafe { _mm_movemask_ps(self.0) as u32 }
+    }
+}
+
+#[cfg(any(not(target_arch = "x86_64"), feature = "f64"))]
+impl NumX4 {
+    pub fn splat(v: Num) -> Self {
+        Self([v; 4])
+    }
+
+    /// Loads the first 4 values of the slice.
+    pub fn load(v: &[Num]) -> Self {
+        Self([v[0], v[1], v[2], v[3]])
+    }
+
+    pub fn to_array(self) -> [Num; 4] {
+        self.0
+    }
+
+    pub fn sqrt(self) -> Self {
+        self.map(|a| a.sqrt())
+    }
+
+    pub fn max(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| a.max(b))
+    }
+
+    pub fn lt(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| mask(a < b))
+    }
+
+    pub fn le(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| mask(a <= b))
+    }
+
+    pub fn ge(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| mask(a >= b))
+    }
+
+    pub fn and(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| mask(a != 0. && b != 0.))
+    }
+
+    pub fn or(self, rhs: Self) -> Self {
+        self.zip(rhs, |a, b| mask(a != 0. || b != 0.))
+    }
+
+    /// Picks lanes from `a` where the mask is set, otherwise from `b`.
+    pub fn select(mask: Self, a: Self, b: Self) -> Self {
+        let mut out = [0.; 4];
+        for i in 0..4 {
+            out[i] = if mask.0[i] != 0. { a.0[i] } else { b.0[i] };
+        }
+        Self(out)
+    }
+
+    /// Packs the mask into the low bits of an int. Lane 0 is bit 0.
+    pub fn bitmask(self) -> u32 {
+        let mut bits = 0;
+        for i in 0..4 {
+            if self.0[i] != 0. {
+                bits |= 1 << i;
+            }
+        }
+        bits
+    }
+
+    fn map(self, f: impl Fn(Num) -> Num) -> Self {
+        Self([f(self.0[0]), f(self.0[1]), f(self.0[2]), f(self.0[3])])
+    }
+
+    fn zip(self, rhs: Self, f: impl Fn(Num, Num) -> Num) -> Self {
+        Self([
+            f(self.0[0], rhs.0[0]),
+            f(self.0[1], rhs.0[1]),
+            f(self.0[2], rhs.0[2]),
+            f(self.0[3], rhs.0[3]),
+        ])
+    }
+}
+
+#[cfg(any(not(target_arch = "x86_64"), feature = "f64"))]
+fn mask(set: bool) -> Num {
+    if set {
+        1.
+    } else {
+        0.
+    }
+}
+
+macro_rules! impl_op {
+    ($trait:ident, $fn:ident, $sse:ident, $avx:ident, $op:tt) => {
+        #[cfg(all(target_arch = "x86_64", target_feature = "avx", not(feature = "f64")))]
+        impl std::ops::$trait for NumX8 {
+            type Output = Self;
+
+            fn $fn(self, rhs: Self) -> Self {
+                unsafe { Self($avx(self.0, rhs.0)) }
+            }
+        }
+
+        #[cfg(not(all(target_arch = "x86_64", target_feature = "avx", not(feature = "f64"))))]
+        impl std::ops::$trait for NumX4 {
+            type Output = Self;
+
+            #[cfg(all(target_arch = "x86_64", not(feature = "f64")))]
+            fn $fn(self, rhs: Self) -> Self {
+                unsafe { Self($sse(self.0, rhs.0)) }
+            }
+
+            #[cfg(any(not(target_arch = "x86_64"), feature = "f64"))]
+            fn $fn(self, rhs: Self) -> Self {
+                self.zip(rhs, |a, b| a $op b)
+            }
+        }
+    };
+}
+
+impl_op!(Add, add, _mm_add_ps, _mm256_add_ps, +);
+impl_op!(Sub, sub, _mm_sub_ps, _mm256_sub_ps, -);
+impl_op!(Mul, mul, _mm_mul_ps, _mm256_mul_ps, *);
+impl_op!(Div, div, _mm_div_ps, _mm256_div_ps, /);
+
+impl std::ops::Neg for Wide {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Wide::splat(0.) - self
+    }
+}
@@ -0,0 +1,80 @@
+use super::{num::Num, vec3::Color};
+use lazy_static::lazy_static;
+
+/// Shortest wavelength sampled, in nanometers
+pub const MIN_WAVELENGTH: Num = 380.;
+/// Longest wavelength sampled, in nanometers
+pub const MAX_WAVELENGTH: Num = 780.;
+
+lazy_static! {
+    /// The RGB a constant spectrum of 1 maps to. Used to keep white surfaces white.
+    static ref WHITE_POINT: Color = {
+        let mut white = Color::default();
+        let mut wavelength = MIN_WAVELENGTH;
+        while wavelength < MAX_WAVELENGTH {
+            white += xyz_to_linear_srgb(cie_xyz(wavelength));
+            wavelength += 1.;
+        }
+
+        white
+    };
+}
+
+/// Maps a uniform random number in `0.0..1.0` to a wavelength.
+pub fn sample_wavelength(u: Num) -> Num {
+    MIN_WAVELENGTH + u * (MAX_WAVELENGTH - MIN_WAVELENGTH)
+}
+
+/// Converts radiance carried at a single sampled wavelength into its RGB contribution.
+pub fn to_rgb(radiance: Num, wavelength: Num) -> Color {
+    let pdf = 1. / (MAX_WAVELENGTH - MIN_WAVELENGTH);
+    let rgb = xyz_to_linear_srgb(cie_xyz(wavelength)) * (radiance / pdf);
+    let white = *WHITE_POINT;
+
+    Color::new(rgb.x / white.x, rgb.y / white.y, rgb.z / white.z)
+}
+
+/// Evaluates an RGB reflectance or radiance at the wavelength.
+/// Uses smooth bands that sum to 1, so white stays a constant spectrum.
+pub fn from_rgb(rgb: Color, wavelength: Num) -> Num {
+    let blue = 1. - smoothstep(470., 510., wavelength);
+    let red = smoothstep(570., 610., wavelength);
+    let green = 1. - blue - red;
+
+    rgb.x * red + rgb.y * green + rgb.z * blue
+}
+
+fn smoothstep(edge0: Num, edge1: Num, x: Num) -> Num {
+    let t = ((x - edge0) / (edge1 - edge0)).max(0.).min(1.);
+    t * t * (3. - 2. * t)
+}
+
+/// CIE 1931 color matching functions, using the multi-lobe fit from
+/// "Simple Analytic Approximations to the CIE XYZ Color Matching Functions" (Wyman et al, 2013).
+fn cie_xyz(wavelength: Num) -> Color {
+    let x = 1.056 * lobe(wavelength, 599.8, 37.9, 31.0)
+        + 0.362 * lobe(wavelength, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(wavelength, 501.1, 20.4, 26.2);
+    let y =
+        0.821 * lobe(wavelength, 568.8, 46.9, 40.5) + 0.286 * lobe(wavelength, 530.9, 16.3, 31.1);
+    let z =
+        1.217 * lobe(wavelength, 437.0, 11.8, 36.0) + 0.681 * lobe(wavelength, 459.0, 26.0, 13.8);
+
+    Color::new(x, y, z)
+}
+
+/// Piecewise gaussian, with a different width on either side of the mean.
+fn lobe(x: Num, mean: Num, sigma_low: Num, sigma_high: Num) -> Num {
+    let sigma = if x < mean { sigma_low } else { sigma_high };
+    let t = (x - mean) / sigma;
+
+    (-0.5 * t * t).exp()
+}
+
+fn xyz_to_linear_srgb(xyz: Color) -> Color {
+    Color::new(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+}
@@ -0,0 +1,154 @@
+// Temporal accumulation. Each pixel's primary hit is reprojected into the previous frame, and the
+// color found there is blended with the new one. History is clamped to the colors around the pixel
+// in the current frame, which rejects most ghosting when things move or get uncovered.
+
+use super::{
+    camera::Camera,
+    num::Num,
+    vec3::{Color, Point3},
+};
+
+/// Settings for temporal accumulation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TemporalSettings {
+    /// How much of the current frame is blended into the history. `1.0` disables accumulation.
+    pub blend: Num,
+    /// History is rejected when its world position is further than this from the new one,
+    /// relative to the distance from the camera.
+    pub rejection_distance: Num,
+}
+
+/// The colors and primary hit positions of the previous frame.
+pub struct History {
+    camera: Option<Camera>,
+    width: u32,
+    height: u32,
+    colors: Vec<Color>,
+    points: Vec<Point3>,
+}
+
+impl History {
+    pub fn new(width: u32, height: u32) -> Self {
+        let len = width as usize * height as usize;
+
+        Self {
+            camera: None,
+            width,
+            height,
+            colors: vec![Color::default(); len],
+            points: vec![Point3::default(); len],
+        }
+    }
+
+    /// Drops the history, so the next frame starts from scratch.
+    pub fn reset(&mut self) {
+        self.camera = None;
+    }
+
+    /// Blends the new colors with the history, then stores the result as the new history.
+    /// `colors` and `points` are the linear colors and primary hit positions of this frame, in row
+    /// major order, and `traced` is which pixels were traced this frame. The rest are carried over from the
+    /// history untouched.
+    pub fn resolve(
+        &mut self,
+        settings: &TemporalSettings,
+        camera: &Camera,
+        colors: &[Color],
+        points: &[Point3],
+        traced: &[bool],
+    ) -> Vec<Color> {
+        let mut resolved = self.colors.clone();
+        let mut resolved_points = self.points.clone();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = self.index(x, y);
+                if !traced[i] {
+                    continue;
+                }
+
+                let current = colors[i];
+                let point = points[i];
+
+                resolved_points[i] = point;
+                resolved[i] = match self.reproject(settings, camera, point) {
+                    Some(history) => {
+                        let (min, max) = self.neighborhood(x, y, colors, traced);
+                        let history = clamp_color(history, min, max);
+
+                        history + settings.blend * (current - history)
+                    }
+                    None => current,
+                };
+            }
+        }
+
+        self.camera = Some(camera.clone());
+        self.colors = resolved.clone();
+        self.points = resolved_points;
+
+        resolved
+    }
+
+    /// Looks up the history color for the world position, if it was visible in the previous frame.
+    fn reproject(
+        &self,
+        settings: &TemporalSettings,
+        camera: &Camera,
+        point: Point3,
+    ) -> Option<Color> {
+        let prev_camera = self.camera.as_ref()?;
+        let (s, t) = prev_camera.project(point)?;
+        if s < 0. || s > 1. || t < 0. || t > 1. {
+            return None;
+        }
+
+        // Inverse of `make_uv`
+        let x = (s * (self.width - 1) as Num).round() as u32;
+        let y = (self.height - 1) - (t * (self.height - 1) as Num).round() as u32;
+        let i = self.index(x, y);
+
+        // Disocclusion; the previous frame saw a different surface there
+        let distance = (self.points[i] - point).len();
+        let max_distance = settings.rejection_distance * (point - camera.eye()).len();
+        if distance > max_distance {
+            return None;
+        }
+
+        Some(self.colors[i])
+    }
+
+    /// The per channel min and max of the traced colors in the 3x3 block around the pixel.
+    fn neighborhood(&self, x: u32, y: u32, colors: &[Color], traced: &[bool]) -> (Color, Color) {
+        let current = colors[self.index(x, y)];
+        let mut min = current;
+        let mut max = current;
+
+        for ny in y.saturating_sub(1)..(y + 2).min(self.height) {
+            for nx in x.saturating_sub(1)..(x + 2).min(self.width) {
+                let i = self.index(nx, ny);
+                if !traced[i] {
+                    continue;
+                }
+
+                let c = colors[i];
+                min = Color::new(min.x.min(c.x), min.y.min(c.y), min.z.min(c.z));
+                max = Color::new(max.x.max(c.x), max.y.max(c.y), max.z.max(c.z));
+            }
+        }
+
+        (min, max)
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        core_conversions::index_2d_to_1d(x as usize, y as usize, self.width as usize)
+    }
+}
+
+fn clamp_color(c: Color, min: Color, max: Color) -> Color {
+    Color::new(
+        c.x.max(min.x).min(max.x),
+        c.y.max(min.y).min(max.y),
+        c.z.max(min.z).min(max.z),
+    )
+}
@@ -0,0 +1,146 @@
+// Guided upscaling. Only one pixel per block of `factor` x `factor` pixels is path traced, the rest are
+// filled with a joint bilateral filter over the nearby traced pixels. The filter is guided by the primary
+// hits of every pixel, which are cheap compared to full paths, so edges stay sharp.
+
+use super::{
+    adaptive::PixelEstimate,
+    hittable::{Hittable, World},
+    num::Num,
+    ray::Ray,
+    vec3::{Color, Vec3},
+    MAX_DRAW, MIN_DRAW,
+};
+
+/// Higher values make the filter stop sooner at creases.
+const NORMAL_POWER: i32 = 8;
+/// Depth differences beyond this fraction of the pixel's own depth are treated as edges.
+const DEPTH_TOLERANCE: Num = 0.1;
+/// Keeps hits right in front of the camera from dividing by zero.
+const MIN_DEPTH: Num = 1e-3;
+/// Weight multiplier for samples on a different material.
+const MATERIAL_MISMATCH: Num = 0.1;
+/// Below this total weight the filter gives up and uses the block's own sample.
+const MIN_WEIGHT: Num = 1e-4;
+
+/// The primary hit of a pixel, used to guide the filter.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Guide {
+    normal: Vec3,
+    depth: Num,
+    /// `None` for the sky
+    material: Option<u32>,
+}
+
+impl Guide {
+    pub fn new(ray: &Ray, world: &World) -> Self {
+        match world.hit(ray, MIN_DRAW, MAX_DRAW) {
+            Some(rec) => Self {
+                normal: rec.normal,
+                depth: rec.t * ray.direction().len(),
+                material: Some(rec.material.id()),
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// How similar the other pixel's surface is to this one, in `0.0..1.0`.
+    fn similarity(&self, other: &Guide) -> Num {
+        match (self.material, other.material) {
+            (None, None) => 1.,
+            (Some(material), Some(other_material)) => {
+                let normal = self.normal.dot(other.normal).max(0.).powi(NORMAL_POWER);
+                let depth_delta = (self.depth - other.depth).abs();
+                let depth = (-depth_delta / (DEPTH_TOLERANCE * self.depth.max(MIN_DEPTH))).exp();
+                let material = if material == other_material {
+                    1.
+                } else {
+                    MATERIAL_MISMATCH
+                };
+
+                normal * depth * material
+            }
+            _ => 0.,
+        }
+    }
+}
+
+/// Whether the pixel is the one that gets traced for its block.
+pub fn is_sample(x: u32, y: u32, width: u32, height: u32, factor: u32) -> bool {
+    x == sample_coord(x / factor, factor, width) && y == sample_coord(y / factor, factor, height)
+}
+
+/// The coordinate of the traced pixel for the block, which is its center, clamped to the image.
+fn sample_coord(block: u32, factor: u32, max: u32) -> u32 {
+    (block * factor + factor / 2).min(max - 1)
+}
+
+/// Fills every traced pixel that isn't a block sample from the samples of its own and neighboring blocks.
+/// `guides` and `estimates` are in row major order. Samples that weren't traced this frame are skipped.
+pub fn upsample(
+    width: u32,
+    height: u32,
+    factor: u32,
+    guides: &[Guide],
+    estimates: &mut [PixelEstimate],
+    is_traced: impl Fn(u32, u32) -> bool,
+) {
+    if factor <= 1 {
+        return;
+    }
+
+    let blocks_x = (width + factor - 1) / factor;
+    let blocks_y = (height + factor - 1) / factor;
+    let index =
+        |x: u32, y: u32| core_conversions::index_2d_to_1d(x as usize, y as usize, width as usize);
+    let mean = |e: &PixelEstimate| e.sum / e.samples().max(1) as Num;
+    let sigma_sqrd = (factor * factor) as Num;
+
+    let mut filled = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            if !is_traced(x, y) || is_sample(x, y, width, height, factor) {
+                continue;
+            }
+
+            let i = index(x, y);
+            let (bx, by) = (x / factor, y / factor);
+
+            let mut sum = Color::default();
+            let mut total = 0.;
+            for nby in by.saturating_sub(1)..(by + 2).min(blocks_y) {
+                for nbx in bx.saturating_sub(1)..(bx + 2).min(blocks_x) {
+                    let sx = sample_coord(nbx, factor, width);
+                    let sy = sample_coord(nby, factor, height);
+                    let j = index(sx, sy);
+                    if !is_traced(sx, sy) || estimates[j].samples() == 0 {
+                        continue;
+                    }
+
+                    let dx = sx as Num - x as Num;
+                    let dy = sy as Num - y as Num;
+                    let spatial = (-(dx * dx + dy * dy) / (2. * sigma_sqrd)).exp();
+                    let w = spatial * guides[i].similarity(&guides[j]);
+
+                    sum += w * mean(&estimates[j]);
+                    total += w;
+                }
+            }
+
+            let own = &estimates[index(
+                sample_coord(bx, factor, width),
+                sample_coord(by, factor, height),
+            )];
+            let color = if total > MIN_WEIGHT {
+                sum / total
+            } else {
+                mean(own)
+            };
+
+            filled.push((i, PixelEstimate::resolved(color, own.stats)));
+        }
+    }
+
+    for (i, estimate) in filled {
+        estimates[i] = estimate;
+    }
+}
@@ -1,6 +1,8 @@
-use super::{ray::Ray, rng};
-
-type Num = f32;
+use super::{
+    num::{Num, PI},
+    ray::Ray,
+    rng,
+};
 
 pub type Point3 = Vec3;
 pub type Color = Vec3;
@@ -21,6 +23,15 @@ impl Vec3 {
         Self { x, y, z }
     }
 
+    /// Converts from single precision, regardless of the precision the raytracer was built with.
+    pub fn from_f32(v: (f32, f32, f32)) -> Self {
+        Self::new(v.0 as Num, v.1 as Num, v.2 as Num)
+    }
+
+    pub fn to_f32(&self) -> (f32, f32, f32) {
+        (self.x as f32, self.y as f32, self.z as f32)
+    }
+
     pub fn unit_y() -> Self {
         (0., 1., 0.).into()
     }
@@ -44,6 +55,20 @@ impl Vec3 {
         }
     }
 
+    /// Applies `e^n` to each component
+    pub fn exp(&self) -> Vec3 {
+        Self {
+            x: self.x.exp(),
+            y: self.y.exp(),
+            z: self.z.exp(),
+        }
+    }
+
+    /// The largest of the three components
+    pub fn max_component(&self) -> Num {
+        self.x.max(self.y).max(self.z)
+    }
+
     /// A normalized vector of length 1
     pub fn unit_vector(&self) -> Vec3 {
         *self / self.len()
@@ -57,7 +82,7 @@ impl Vec3 {
         }
     }
 
-    pub fn random_range(min: f32, max: f32) -> Self {
+    pub fn random_range(min: Num, max: Num) -> Self {
         Self {
             x: rng::random_range(min, max),
             y: rng::random_range(min, max),
@@ -90,8 +115,39 @@ impl Vec3 {
         }
     }
 
+    /// A random point in the unit disk on the XY plane.
+    pub fn random_in_unit_disk() -> Self {
+        let r = rng::random().sqrt();
+        let phi = 2. * PI * rng::random();
+
+        Self {
+            x: phi.cos() * r,
+            y: phi.sin() * r,
+            z: 0.,
+        }
+    }
+
+    /// A random direction in the +Z hemisphere, distributed proportional to the cosine of the angle with +Z.
+    pub fn random_cosine_direction() -> Self {
+        let r1 = rng::random();
+        let r2 = rng::random();
+        let phi = 2. * PI * r1;
+        let r = r2.sqrt();
+
+        Self {
+            x: phi.cos() * r,
+            y: phi.sin() * r,
+            z: (1. - r2).sqrt(),
+        }
+    }
+
+    /// Whether no component is infinite or NaN
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
     pub fn near_zero(&self) -> bool {
-        let near_zero: f32 = 1e-8;
+        let near_zero: Num = 1e-8;
         self.x.abs() < near_zero && self.y.abs() < near_zero && self.z.abs() < near_zero
     }
 
@@ -99,7 +155,7 @@ impl Vec3 {
         *self - 2. * self.dot(n) * n
     }
 
-    pub fn refract(&self, n: Self, etai_over_etat: f32) -> Self {
+    pub fn refract(&self, n: Self, etai_over_etat: Num) -> Self {
         let uv = *self;
         let cos_theta = (-uv).dot(n).min(1.);
         let r_out_perp = etai_over_etat * (uv + cos_theta * n);
@@ -118,7 +174,7 @@ impl Default for Vec3 {
     }
 }
 
-impl Into<Vec3> for (f32, f32, f32) {
+impl Into<Vec3> for (Num, Num, Num) {
     fn into(self) -> Vec3 {
         Vec3::new(self.0, self.1, self.2)
     }
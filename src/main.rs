@@ -1,22 +1,43 @@
 #[macro_use]
 mod profiling;
 
+mod actions;
+mod camera_controllers;
+mod cfg;
 mod core_raytracer;
 mod renderer;
+mod scene;
 
-use core_raytracer::Raytracer;
+use core_fs::FileWatcher;
+use core_raytracer::{
+    AdaptiveSampling, BounceLimits, DebugMode, IntegratorMode, Interlace, Materials, ObjectHandle,
+    Projection, Raytracer, TemporalSettings, World, MATERIALS_PATH,
+};
 use core_renderer::RenderBuilder;
-use core_simulation::{Simulation, SimulationExecutor};
+use core_simulation::{ActionMsg, Bindings, MouseButton, Simulation, SimulationExecutor};
 use core_time::{duration_from_hz, Duration, Timer};
 use core_wingfx::WinGfx;
 
+use actions::Action;
+use camera_controllers::{
+    CameraPose, CameraTrack, FlyCamera, FlyCameraSettings, OrbitCamera, OrbitCameraSettings,
+};
+
 use renderer::{RtcrRenderer, Size};
+use scene::{Scene, SCENE_PATH};
 
 const MAX_RENDER_SCALAR: u32 = 120;
 const MIN_RENDER_SCALAR: u32 = 12;
 
 const PERF_DUMP_OCCURENCE: u64 = 100;
 
+/// How far the cursor may move between pressing and releasing the left button for it to count as a click, in
+/// window pixels. Further than this is a drag.
+const MAX_CLICK_DISTANCE: f32 = 4.;
+
+/// How often the scene and materials files are checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
 fn main() {
     let max_engine_msgs = 256;
     let sim_hz = Some(60);
@@ -26,6 +47,29 @@ fn main() {
     let window_height = 1080;
     let initial_render_scalar = 120; // This gets a 16x9 character render on a 16:9 render ratio
 
+    // `--render-animation <dir>` renders every frame of the scene's camera animation to the directory, instead of
+    // opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let animation_output = match args.as_slice() {
+        [] => None,
+        [flag, output] if flag == "--render-animation" => Some(output.as_str()),
+        _ => {
+            println!("Usage: rt_cputracer [--render-animation <output dir>]");
+            return;
+        }
+    };
+    let animation_fps = 30.;
+    let animation_samples = 64;
+    let animation_size = Size {
+        width: 480,
+        height: 270,
+    };
+
+    if let Some(output) = animation_output {
+        render_animation(output, animation_fps, animation_samples, animation_size);
+        return;
+    }
+
     let cfg = Cfg {
         initial_render_scalar,
         window_width,
@@ -41,8 +85,23 @@ fn main() {
 }
 
 struct Sim<'a> {
-    y: f32,
-    x: f32,
+    bindings: Bindings<Action>,
+    /// The scene's camera animation, if it has one
+    animation: Option<CameraTrack>,
+    /// Seconds since the animation started playing
+    animation_time: f32,
+    fly_camera: FlyCamera,
+    orbit_camera: OrbitCamera,
+    /// The controller that was used last, which drives the raytracer's camera
+    active_camera: ActiveCamera,
+    /// Last cursor position, in window pixels
+    cursor: (f32, f32),
+    /// Where the left button was pressed, while it's held
+    left_press: Option<(f32, f32)>,
+    /// The last object picked
+    selected: Option<ObjectHandle>,
+    /// Watches the scene and materials files and the images they use, so they're reloaded when edited
+    scene_watcher: FileWatcher,
     /// If set, scales the resolution until it reaches the target duration
     dynamic_scaling_duration: Option<Duration>,
 
@@ -60,15 +119,29 @@ struct Cfg {
 }
 enum Msg {}
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ActiveCamera {
+    Fly,
+    Orbit,
+    Animation,
+}
+
 //asdf
 impl<'a> Simulation<Cfg, Msg> for Sim<'a> {
     fn new(config: Cfg) -> Self {
         // Rest of program
-        let aa_samples = 0;
-        let debug_normals = false;
-        let post_process_aa = true;
         let save_renders = false;
-        let primary_ray_strength = 5;
+        let camera_settings = FlyCameraSettings {
+            max_speed: 2.,
+            acceleration: 8.,
+            turn_speed: 1.5,
+        };
+        let orbit_settings = OrbitCameraSettings {
+            rotate_speed: 0.01,
+            pan_speed: 0.002,
+            zoom_speed: 0.1,
+            min_distance: 0.1,
+        };
 
         let dynamic_scaling_duration = Some(duration_from_hz(90));
 
@@ -84,25 +157,54 @@ impl<'a> Simulation<Cfg, Msg> for Sim<'a> {
             font,
             font_size,
         );
-        let raytracer = core_raytracer::build(
-            renderer.size(),
-            aa_samples,
-            post_process_aa,
-            primary_ray_strength,
-            debug_normals,
-        );
+        let mut raytracer = build_raytracer(renderer.size(), None);
+
+        let mut scene_watcher = FileWatcher::new(RELOAD_INTERVAL);
+        scene_watcher.watch(SCENE_PATH);
+        scene_watcher.watch(MATERIALS_PATH);
+
+        let animation = match load_scene() {
+            Ok((scene, world)) => {
+                if let Some(world) = world {
+                    *raytracer.world_mut() = world;
+                }
+                for heightfield in &scene.heightfields {
+                    scene_watcher.watch(&heightfield.image);
+                }
+                scene.camera_track
+            }
+            Err(e) => {
+                println!("{}", e);
+                None
+            }
+        };
+        let active_camera = match animation {
+            Some(_) => ActiveCamera::Animation,
+            None => ActiveCamera::Fly,
+        };
+
+        let eye = (-3., 0.1, 1.);
+        let target = (0., 0., 0.);
+        let fly_camera = FlyCamera::new(eye, target, camera_settings);
+        let orbit_camera = OrbitCamera::new(eye, target, orbit_settings);
 
-        let mut y = 0.1;
-        let x = -3.;
         Self {
+            bindings: actions::load_bindings(),
+            animation,
+            animation_time: 0.,
+            cursor: (0., 0.),
+            left_press: None,
+            selected: None,
+            scene_watcher,
             frame: 0,
             dynamic_scaling_duration,
             window_size: Size {
                 width: config.window_width,
                 height: config.window_height,
             },
-            y,
-            x,
+            fly_camera,
+            orbit_camera,
+            active_camera,
             render_scalar: config.initial_render_scalar,
             raytracer,
             renderer,
@@ -117,28 +219,89 @@ impl<'a> Simulation<Cfg, Msg> for Sim<'a> {
     ) -> core_simulation::ControlMessage {
         perf!("simulation - tick");
         self.frame = frame;
-        self.y += 0.01;
-        self.x += 0.01;
-        let eye = (self.x, self.y, 1.);
-        let target = (0., 0., 0.);
 
         for msg in messages {
             match msg {
                 core_simulation::Input::UserMsg(_) => {}
-                core_simulation::Input::WindowMsg(win_msg) => match win_msg {
-                    core_simulation::WindowMsg::Shutdown => {}
-                    core_simulation::WindowMsg::RedrawRequested => {}
-                    core_simulation::WindowMsg::Resize { w, h } => {
-                        self.renderer.resize(*w, *h, self.render_scalar);
-                        self.raytracer.resize(self.renderer.size());
+                core_simulation::Input::WindowMsg(win_msg) => {
+                    if let Some(action_msg) = self.bindings.handle(win_msg) {
+                        self.handle_action(action_msg);
+                    }
+
+                    match win_msg {
+                        core_simulation::WindowMsg::Shutdown => {}
+                        core_simulation::WindowMsg::RedrawRequested => {}
+                        core_simulation::WindowMsg::Resize { w, h } => {
+                            self.window_size = Size {
+                                width: *w,
+                                height: *h,
+                            };
+                            self.renderer.resize(*w, *h, self.render_scalar);
+                            self.raytracer.resize(self.renderer.size());
+                        }
+                        // Keys are handled through the bindings
+                        core_simulation::WindowMsg::KeyPress(_) => {}
+                        core_simulation::WindowMsg::KeyRelease(_) => {}
+                        core_simulation::WindowMsg::ModifiersChanged(_) => {}
+                        core_simulation::WindowMsg::CursorMove { x, y } => {
+                            self.cursor = (*x, *y);
+
+                            // Dragging takes over from the other cameras, but clicking to pick doesn't
+                            if self.orbit_camera.is_dragging()
+                                && !self.is_clicking()
+                                && self.active_camera != ActiveCamera::Orbit
+                            {
+                                self.activate_camera(ActiveCamera::Orbit);
+                            }
+                            self.orbit_camera.cursor_moved(*x, *y);
+                        }
+                        core_simulation::WindowMsg::MousePress(button) => {
+                            if *button == MouseButton::Left {
+                                self.left_press = Some(self.cursor);
+                            }
+
+                            self.orbit_camera.button(*button, true);
+                        }
+                        core_simulation::WindowMsg::MouseRelease(button) => {
+                            if *button == MouseButton::Left {
+                                if self.is_clicking() {
+                                    self.pick_at_cursor();
+                                }
+                                self.left_press = None;
+                            }
+
+                            self.orbit_camera.button(*button, false);
+                        }
+                        core_simulation::WindowMsg::MouseWheel { lines } => {
+                            self.activate_camera(ActiveCamera::Orbit);
+                            self.orbit_camera.scroll(*lines);
+                        }
                     }
-                    core_simulation::WindowMsg::KeyPress(_) => {}
-                    core_simulation::WindowMsg::KeyRelease(_) => {}
-                },
+                }
             }
         }
 
-        self.raytracer.look_at(eye, target, None);
+        if !self.scene_watcher.poll().is_empty() {
+            self.reload_scene();
+        }
+
+        match self.active_camera {
+            ActiveCamera::Fly => {
+                self.fly_camera.update(delta_t.as_secs_f32());
+                self.raytracer
+                    .look_at(self.fly_camera.eye(), self.fly_camera.target(), None);
+            }
+            ActiveCamera::Orbit => {
+                self.raytracer
+                    .look_at(self.orbit_camera.eye(), self.orbit_camera.target(), None);
+            }
+            ActiveCamera::Animation => {
+                self.animation_time += delta_t.as_secs_f32();
+                if let Some(pose) = self.animation_pose() {
+                    apply_pose(&mut self.raytracer, &pose);
+                }
+            }
+        }
 
         // Write the perf metrics every 100 frames
         #[cfg(feature = "profiling")]
@@ -196,3 +359,262 @@ impl<'a> Simulation<Cfg, Msg> for Sim<'a> {
         }
     }
 }
+
+impl<'a> Sim<'a> {
+    /// Hands the camera over to the given controller, starting it from where the current one left off.
+    fn activate_camera(&mut self, camera: ActiveCamera) {
+        if self.active_camera == camera {
+            return;
+        }
+
+        let (eye, target) = match self.active_camera {
+            ActiveCamera::Fly => (self.fly_camera.eye(), self.fly_camera.target()),
+            ActiveCamera::Orbit => (self.orbit_camera.eye(), self.orbit_camera.target()),
+            ActiveCamera::Animation => match self.animation_pose() {
+                Some(pose) => (pose.eye, pose.target),
+                None => (self.fly_camera.eye(), self.fly_camera.target()),
+            },
+        };
+
+        match camera {
+            ActiveCamera::Fly => self.fly_camera.set_pose(eye, target),
+            ActiveCamera::Orbit => self.orbit_camera.set_eye(eye),
+            ActiveCamera::Animation => self.animation_time = 0.,
+        }
+
+        self.active_camera = camera;
+    }
+
+    /// The pose of the looping camera animation.
+    fn animation_pose(&self) -> Option<CameraPose> {
+        let track = self.animation.as_ref()?;
+        let duration = track.duration();
+        let time = if duration > 0. {
+            self.animation_time % duration
+        } else {
+            0.
+        };
+
+        Some(track.sample(track.start_time() + time))
+    }
+
+    /// Reloads the scene and materials into the running raytracer, keeping the camera where it is.
+    /// If either file has an error, it's printed and the current scene is kept. If the scene has no objects, a new
+    /// scene is generated.
+    fn reload_scene(&mut self) {
+        let (scene, world) = match load_scene() {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                println!("{}. Keeping the current scene.", e);
+                return;
+            }
+        };
+
+        for heightfield in &scene.heightfields {
+            self.scene_watcher.watch(&heightfield.image);
+        }
+
+        self.animation = scene.camera_track;
+        if self.active_camera == ActiveCamera::Animation && self.animation.is_none() {
+            self.activate_camera(ActiveCamera::Fly);
+        }
+
+        // Without any objects in the file, the scene goes back to the generated one
+        *self.raytracer.world_mut() = world.unwrap_or_else(core_raytracer::generate_world);
+        self.selected = None;
+
+        println!("Reloaded {}", SCENE_PATH);
+    }
+
+    /// Whether the left button is held, without having moved far enough since it was pressed to be a drag.
+    fn is_clicking(&self) -> bool {
+        self.left_press.map_or(false, |press| {
+            let (dx, dy) = (self.cursor.0 - press.0, self.cursor.1 - press.1);
+            (dx * dx + dy * dy).sqrt() <= MAX_CLICK_DISTANCE
+        })
+    }
+
+    /// Reports the object under the cursor.
+    fn pick_at_cursor(&mut self) {
+        let x = self.cursor.0 / self.window_size.width.max(1) as f32;
+        let y = self.cursor.1 / self.window_size.height.max(1) as f32;
+
+        let pick = self.raytracer.pick(x, y);
+        self.selected = pick.map(|pick| pick.object);
+
+        match pick {
+            Some(pick) => println!(
+                "Picked {:?} at {:?}, {:.2} away. Normal {:?}, material {:?}",
+                pick.object, pick.point, pick.distance, pick.normal, pick.material
+            ),
+            None => println!("Picked nothing"),
+        }
+    }
+
+    fn handle_action(&mut self, action_msg: ActionMsg<Action>) {
+        match action_msg {
+            ActionMsg::Pressed(Action::Fly(control)) => {
+                self.activate_camera(ActiveCamera::Fly);
+                self.fly_camera.set_control(control, true);
+            }
+            ActionMsg::Released(Action::Fly(control)) => {
+                self.fly_camera.set_control(control, false);
+            }
+            ActionMsg::Pressed(Action::DebugView(debug_mode)) => {
+                self.raytracer.set_debug_mode(debug_mode);
+            }
+            ActionMsg::Pressed(Action::ToggleSpectral) => {
+                let integrator_mode = match self.raytracer.integrator_mode() {
+                    IntegratorMode::Rgb => IntegratorMode::Spectral,
+                    IntegratorMode::Spectral => IntegratorMode::Rgb,
+                };
+                self.raytracer.set_integrator_mode(integrator_mode);
+            }
+            ActionMsg::Pressed(Action::CycleInterlace) => {
+                let interlace = self.raytracer.interlace().next();
+                self.raytracer.set_interlace(interlace);
+            }
+            ActionMsg::Pressed(Action::CycleProjection) => {
+                let projection = next_projection(self.raytracer.projection());
+                self.raytracer.set_projection(projection);
+            }
+            ActionMsg::Pressed(Action::RemoveSelected) => {
+                if let Some(selected) = self.selected.take() {
+                    self.raytracer.world_mut().remove(selected);
+                }
+            }
+            ActionMsg::Released(_) => {}
+        }
+    }
+}
+
+/// Builds the raytracer. Tweak the settings here.
+/// Offline renders give `offline_samples`, and trace every pixel of every frame with that many samples, as each
+/// image has to be complete on its own.
+fn build_raytracer(render_size: Size, offline_samples: Option<u32>) -> Raytracer {
+    let aa_samples = offline_samples.unwrap_or(0);
+    // Overrides `aa_samples` when set
+    let adaptive_sampling = match offline_samples {
+        Some(_) => None,
+        None => Some(AdaptiveSampling {
+            min_samples: 2,
+            max_samples: 16,
+            error_threshold: 0.05,
+            frame_budget: 4096,
+        }),
+    };
+    let debug_mode = DebugMode::Off;
+    let projection = Projection::Perspective;
+    let interlace = Interlace::Off;
+    let temporal = match offline_samples {
+        Some(_) => None,
+        None => Some(TemporalSettings {
+            blend: 0.2,
+            rejection_distance: 0.05,
+        }),
+    };
+    // Path trace 1 in every N x N pixels, filling the rest from their neighbors
+    let upscale_factor = if offline_samples.is_some() { 1 } else { 2 };
+    let post_process_aa = true;
+    let primary_ray_strength = 5;
+    let bounce_limits = BounceLimits {
+        diffuse: 8,
+        specular: 16,
+        transmission: 16,
+        total: 50,
+        roulette_depth: 3,
+    };
+    let integrator_mode = IntegratorMode::Rgb;
+
+    core_raytracer::build(
+        render_size,
+        aa_samples,
+        adaptive_sampling,
+        interlace,
+        temporal,
+        upscale_factor,
+        post_process_aa,
+        primary_ray_strength,
+        bounce_limits,
+        integrator_mode,
+        debug_mode,
+        projection,
+    )
+}
+
+/// Loads the scene file, along with a world built from its objects. The world is `None` if the scene has no objects,
+/// in which case the materials file isn't needed.
+fn load_scene() -> Result<(Scene, Option<World>), String> {
+    let scene = Scene::load(SCENE_PATH)?;
+    if !scene.has_objects() {
+        return Ok((scene, None));
+    }
+
+    let materials = Materials::load(MATERIALS_PATH)?;
+    let world = core_raytracer::build_world(&scene, &materials)
+        .map_err(|e| format!("{}: {}", SCENE_PATH, e))?;
+
+    Ok((scene, Some(world)))
+}
+
+/// Points the raytracer's camera as posed.
+fn apply_pose(raytracer: &mut Raytracer, pose: &CameraPose) {
+    raytracer.look_at(pose.eye, pose.target, Some(pose.up));
+    raytracer.set_v_fov(pose.v_fov_deg);
+    raytracer.set_focus(pose.focus_distance, pose.aperture);
+}
+
+/// Renders the scene's camera animation to a numbered image sequence in `output`, with `samples` per pixel.
+fn render_animation(output: &str, fps: f32, samples: u32, size: Size) {
+    let (track, world) = match load_scene() {
+        Ok((
+            Scene {
+                camera_track: Some(track),
+                ..
+            },
+            world,
+        )) => (track, world),
+        Ok(_) => {
+            println!("{} has no camera animation", SCENE_PATH);
+            return;
+        }
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = std::fs::create_dir_all(output) {
+        println!("Unable to create {}: {}", output, e);
+        return;
+    }
+
+    let mut raytracer = build_raytracer(size, Some(samples));
+    if let Some(world) = world {
+        *raytracer.world_mut() = world;
+    }
+
+    let frames = (track.duration() * fps).floor() as u32 + 1;
+    for frame in 0..frames {
+        let pose = track.sample(track.start_time() + frame as f32 / fps);
+        apply_pose(&mut raytracer, &pose);
+
+        let path = format!("{}/frame_{:05}.png", output, frame);
+        if let Err(e) = raytracer.render_image().save(&path) {
+            println!("Unable to save {}: {}", path, e);
+            return;
+        }
+
+        println!("Rendered {} ({}/{})", path, frame + 1, frames);
+    }
+}
+
+/// Cycles through the camera projections.
+fn next_projection(projection: Projection) -> Projection {
+    match projection {
+        Projection::Perspective => Projection::Orthographic { height: 4. },
+        Projection::Orthographic { .. } => Projection::Fisheye { fov_deg: 180. },
+        Projection::Fisheye { .. } => Projection::Equirectangular,
+        Projection::Equirectangular => Projection::Perspective,
+    }
+}
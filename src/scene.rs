@@ -0,0 +1,319 @@
+// The scene file, in the `cfg` format. The `[camera]` section sets how the camera is animated, and each
+// `[keyframe]` section adds a keyframe to that animation. Fields left out of a keyframe are kept from the previous
+// one. Each `[sphere]` and `[heightfield]` section adds an object, using a material from the materials file.
+
+use crate::camera_controllers::{CameraKeyframe, CameraTrack, Interpolation};
+use crate::cfg::{self, CfgError, Section};
+
+pub const SCENE_PATH: &str = "res/scene.cfg";
+
+#[derive(Default)]
+pub struct Scene {
+    pub camera_track: Option<CameraTrack>,
+    pub spheres: Vec<SceneSphere>,
+    pub heightfields: Vec<SceneHeightfield>,
+}
+
+pub struct SceneSphere {
+    /// The line of its `[sphere]` header
+    pub line: usize,
+    pub center: (f32, f32, f32),
+    pub radius: f32,
+    /// Name of the material in the materials file
+    pub material: String,
+}
+
+/// Terrain built from a grayscale image.
+pub struct SceneHeightfield {
+    /// The line of its `[heightfield]` header
+    pub line: usize,
+    pub image: String,
+    /// The lowest corner
+    pub corner: (f32, f32, f32),
+    /// Width, maximum height and depth
+    pub size: (f32, f32, f32),
+    pub material: String,
+}
+
+impl Scene {
+    /// Reads and parses the scene file, formatting any error for display.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+
+        Self::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Whether the scene has any objects. If not, the generated scene is used.
+    pub fn has_objects(&self) -> bool {
+        !self.spheres.is_empty() || !self.heightfields.is_empty()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, CfgError> {
+        let mut interpolation = Interpolation::CatmullRom;
+        let mut keyframes: Vec<CameraKeyframe> = vec![];
+        let mut spheres = vec![];
+        let mut heightfields = vec![];
+
+        for section in cfg::sections(text)? {
+            match section.name {
+                "camera" => {
+                    for field in &section.fields {
+                        match field.name {
+                            "interpolation" => {
+                                interpolation = match field.value {
+                                    "linear" => Interpolation::Linear,
+                                    "catmull_rom" => Interpolation::CatmullRom,
+                                    "bezier" => Interpolation::Bezier,
+                                    _ => return Err(field.invalid()),
+                                }
+                            }
+                            _ => return Err(field.unknown()),
+                        }
+                    }
+                }
+                "keyframe" => {
+                    let keyframe = parse_keyframe(&section, keyframes.last())?;
+                    keyframes.push(keyframe);
+                }
+                "sphere" => spheres.push(parse_sphere(&section)?),
+                "heightfield" => heightfields.push(parse_heightfield(&section)?),
+                _ => return Err(section.unknown()),
+            }
+        }
+
+        Ok(Self {
+            camera_track: CameraTrack::new(interpolation, keyframes),
+            spheres,
+            heightfields,
+        })
+    }
+}
+
+/// Parses a keyframe, starting from the previous one. The first keyframe has to place the camera.
+fn parse_keyframe(
+    section: &Section,
+    previous: Option<&CameraKeyframe>,
+) -> Result<CameraKeyframe, CfgError> {
+    let mut time = None;
+    let mut pose = previous.map(|k| k.pose).unwrap_or_default();
+
+    for field in &section.fields {
+        match field.name {
+            "time" => {
+                let t = field.num()?;
+
+                // Keyframes have to be in order
+                if previous.map_or(false, |previous| t <= previous.time) {
+                    return Err(field.invalid());
+                }
+
+                time = Some(t);
+            }
+            "eye" => pose.eye = field.vec3()?,
+            "target" => pose.target = field.vec3()?,
+            "up" => pose.up = field.vec3()?,
+            "fov" => pose.v_fov_deg = field.num()?,
+            "focus_distance" => pose.focus_distance = field.num()?,
+            "aperture" => pose.aperture = field.num()?,
+            _ => return Err(field.unknown()),
+        }
+    }
+
+    if previous.is_none() {
+        for required in &["eye", "target"] {
+            if section.get(required).is_none() {
+                return Err(section.missing(required));
+            }
+        }
+    }
+
+    Ok(CameraKeyframe {
+        time: time.ok_or_else(|| section.missing("time"))?,
+        pose,
+    })
+}
+
+fn parse_sphere(section: &Section) -> Result<SceneSphere, CfgError> {
+    for field in &section.fields {
+        match field.name {
+            "center" | "radius" | "material" => {}
+            _ => return Err(field.unknown()),
+        }
+    }
+
+    let field = |name| section.get(name).ok_or_else(|| section.missing(name));
+
+    Ok(SceneSphere {
+        line: section.line,
+        center: field("center")?.vec3()?,
+        radius: field("radius")?.num()?,
+        material: field("material")?.value.to_string(),
+    })
+}
+
+fn parse_heightfield(section: &Section) -> Result<SceneHeightfield, CfgError> {
+    for field in &section.fields {
+        match field.name {
+            "image" | "corner" | "size" | "material" => {}
+            _ => return Err(field.unknown()),
+        }
+    }
+
+    let field = |name| section.get(name).ok_or_else(|| section.missing(name));
+
+    let size_field = field("size")?;
+    let size = size_field.vec3()?;
+    if size.0 <= 0. || size.1 <= 0. || size.2 <= 0. {
+        return Err(size_field.invalid());
+    }
+
+    Ok(SceneHeightfield {
+        line: section.line,
+        image: field("image")?.value.to_string(),
+        corner: field("corner")?.vec3()?,
+        size,
+        material: field("material")?.value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_keyframes() {
+        let text = "[camera]\ninterpolation = linear\n\
+                    [keyframe]\ntime = 1\neye = 0, 0, 2\ntarget = 0, 0, 0\nfov = 60\n\
+                    [keyframe]\ntime = 3\neye = 0, 0, 4\n";
+        let track = Scene::parse(text).unwrap().camera_track.unwrap();
+
+        assert_eq!(1., track.start_time());
+        assert_eq!(2., track.duration());
+
+        // Fields left out are kept from the previous keyframe
+        let pose = track.sample(2.);
+        assert_eq!((0., 0., 3.), pose.eye);
+        assert_eq!((0., 0., 0.), pose.target);
+        assert_eq!(60., pose.v_fov_deg);
+    }
+
+    #[test]
+    fn scene_keyframes_out_of_order() {
+        let keyframes = |second_time: &str| {
+            format!(
+                "[keyframe]\ntime = 2\neye = 0, 0, 2\ntarget = 0, 0, 0\n[keyframe]\ntime = {}\n",
+                second_time
+            )
+        };
+
+        for time in ["1", "2"].iter() {
+            assert_eq!(
+                Some(CfgError::InvalidValue {
+                    line: 6,
+                    field: "time".to_string()
+                }),
+                Scene::parse(&keyframes(time)).err()
+            );
+        }
+        assert!(Scene::parse(&keyframes("3")).is_ok());
+    }
+
+    #[test]
+    fn scene_missing_fields() {
+        let missing = |text: &str, line: usize, field: &str| {
+            assert_eq!(
+                Some(CfgError::MissingField {
+                    line,
+                    field: field.to_string()
+                }),
+                Scene::parse(text).err()
+            );
+        };
+
+        // Only the first keyframe has to place the camera, but every keyframe needs a time
+        missing("[keyframe]\ntime = 0\ntarget = 0, 0, 0\n", 1, "eye");
+        missing("[keyframe]\ntime = 0\neye = 0, 0, 2\n", 1, "target");
+        missing(
+            "[keyframe]\ntime = 0\neye = 0, 0, 2\ntarget = 0, 0, 0\n[keyframe]\neye = 1, 0, 2\n",
+            5,
+            "time",
+        );
+
+        missing(
+            "\n[sphere]\ncenter = 0, 0, 0\nmaterial = ground\n",
+            2,
+            "radius",
+        );
+        missing(
+            "[heightfield]\nimage = res/terrain.png\ncorner = 0, 0, 0\nmaterial = ground\n",
+            1,
+            "size",
+        );
+    }
+
+    #[test]
+    fn scene_objects() {
+        let text = "[sphere]\ncenter = 0, -100.5, -1\nradius = 100\nmaterial = ground\n\
+                    [heightfield]\nimage = terrain.png\ncorner = -2, -0.5, -2\nsize = 4, 1, 4\nmaterial = rock\n";
+        let scene = Scene::parse(text).unwrap();
+
+        assert!(scene.has_objects());
+        assert!(scene.camera_track.is_none());
+        assert_eq!(1, scene.spheres.len());
+        assert_eq!(1, scene.spheres[0].line);
+        assert_eq!(100., scene.spheres[0].radius);
+        assert_eq!("ground", scene.spheres[0].material);
+        assert_eq!(1, scene.heightfields.len());
+        assert_eq!(5, scene.heightfields[0].line);
+        assert_eq!((4., 1., 4.), scene.heightfields[0].size);
+
+        assert!(!Scene::parse("[camera]\n").unwrap().has_objects());
+    }
+
+    #[test]
+    fn scene_unknown_names() {
+        assert_eq!(
+            Some(CfgError::UnknownSection {
+                line: 1,
+                section: "light".to_string()
+            }),
+            Scene::parse("[light]\n").err()
+        );
+        assert_eq!(
+            Some(CfgError::UnknownField {
+                line: 3,
+                field: "color".to_string()
+            }),
+            Scene::parse("[sphere]\nradius = 1\ncolor = 1, 0, 0\n").err()
+        );
+        assert_eq!(
+            Some(CfgError::InvalidValue {
+                line: 2,
+                field: "interpolation".to_string()
+            }),
+            Scene::parse("[camera]\ninterpolation = cubic\n").err()
+        );
+    }
+
+    #[test]
+    fn scene_heightfield_size_is_positive() {
+        let heightfield = |size: &str| {
+            Scene::parse(&format!(
+                "[heightfield]\nimage = terrain.png\ncorner = 0, 0, 0\nsize = {}\nmaterial = rock\n",
+                size
+            ))
+        };
+
+        assert!(heightfield("4, 1, 4").is_ok());
+        for size in ["4, 0, 4", "-4, 1, 4", "4, 1, 0"].iter() {
+            assert_eq!(
+                Some(CfgError::InvalidValue {
+                    line: 4,
+                    field: "size".to_string()
+                }),
+                heightfield(size).err()
+            );
+        }
+    }
+}